@@ -0,0 +1,4 @@
+CREATE TABLE name_claims(
+    username TEXT PRIMARY KEY,
+    token_hash TEXT NOT NULL
+);
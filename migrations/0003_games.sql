@@ -0,0 +1,17 @@
+CREATE TABLE games(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    time_in_seconds INTEGER NOT NULL,
+    difficulty TEXT NOT NULL,
+    size TEXT NOT NULL,
+    finished_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX games_by_username ON games(username, finished_at);
+
+-- every score so far was a won game, though when it was played wasn't recorded
+INSERT INTO games(username, outcome, time_in_seconds, difficulty, size)
+SELECT username, 'won', time_in_seconds, difficulty, size
+FROM scores
+ORDER BY id;
@@ -0,0 +1,19 @@
+-- sqlite can't add a column defaulting to the current time, so the table is rebuilt.
+-- Scores posted before now have no known date and only count towards all-time boards.
+CREATE TABLE scores_dated(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    username TEXT NOT NULL,
+    time_in_seconds INTEGER NOT NULL,
+    difficulty TEXT NOT NULL,
+    size TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO scores_dated(id, username, time_in_seconds, difficulty, size, created_at)
+SELECT id, username, time_in_seconds, difficulty, size, '1970-01-01 00:00:00'
+FROM scores;
+
+DROP TABLE scores;
+ALTER TABLE scores_dated RENAME TO scores;
+
+CREATE INDEX scores_by_board ON scores(difficulty, size, created_at);
@@ -0,0 +1,2 @@
+-- supports finding each player's personal best on a board
+CREATE INDEX scores_by_player ON scores(difficulty, size, username, time_in_seconds);
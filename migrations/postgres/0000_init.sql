@@ -0,0 +1,7 @@
+CREATE TABLE scores(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    username TEXT NOT NULL,
+    time_in_seconds BIGINT NOT NULL,
+    difficulty TEXT NOT NULL,
+    size TEXT NOT NULL
+);
@@ -0,0 +1,18 @@
+-- timestamps are kept as text in sqlite's CURRENT_TIMESTAMP format, so both databases
+-- compare and display them the same way
+CREATE TABLE games(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    username TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    time_in_seconds BIGINT NOT NULL,
+    difficulty TEXT NOT NULL,
+    size TEXT NOT NULL,
+    finished_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+CREATE INDEX games_by_username ON games(username, finished_at);
+
+INSERT INTO games(username, outcome, time_in_seconds, difficulty, size)
+SELECT username, 'won', time_in_seconds, difficulty, size
+FROM scores
+ORDER BY id;
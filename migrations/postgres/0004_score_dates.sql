@@ -0,0 +1,6 @@
+-- Scores posted before now have no known date and only count towards all-time boards.
+ALTER TABLE scores ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE scores
+    ALTER COLUMN created_at SET DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS');
+
+CREATE INDEX scores_by_board ON scores(difficulty, size, created_at);
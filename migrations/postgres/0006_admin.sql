@@ -0,0 +1,25 @@
+-- the login the admin console last ran with, so its sessions can be ended when that changes
+CREATE TABLE admin_credentials(
+    username TEXT PRIMARY KEY,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE admin_sessions(
+    token_hash TEXT PRIMARY KEY,
+    admin TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE TABLE banned_names(
+    username TEXT PRIMARY KEY,
+    banned_by TEXT NOT NULL,
+    banned_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
+
+CREATE TABLE audit_log(
+    id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    admin TEXT NOT NULL,
+    action TEXT NOT NULL,
+    details TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS')
+);
@@ -0,0 +1,6 @@
+-- ids sent by clients with won games, so a score that's sent again isn't counted twice
+ALTER TABLE games ADD COLUMN game_id TEXT;
+ALTER TABLE scores ADD COLUMN game_id TEXT;
+
+CREATE UNIQUE INDEX games_by_game_id ON games(game_id);
+CREATE UNIQUE INDEX scores_by_game_id ON scores(game_id);
@@ -0,0 +1,84 @@
+// Keeps the app shell cached so the game can be loaded and played offline. The game runs
+// entirely in the browser, and scores won offline are queued and sent later by the app.
+
+const CACHE = "rustsweeper-v2";
+
+// cargo-leptos builds the stylesheet and Tailwind's output into rustsweeper.css
+const SHELL = [
+    "/",
+    "/pkg/rustsweeper.js",
+    "/pkg/rustsweeper_bg.wasm",
+    "/pkg/rustsweeper.css",
+    "/favicon.ico",
+    "/icon.svg",
+    "/manifest.webmanifest",
+];
+
+self.addEventListener("install", (event) => {
+    event.waitUntil(
+        caches.open(CACHE)
+            .then(cacheShell)
+            .then(() => self.skipWaiting()),
+    );
+});
+
+self.addEventListener("activate", (event) => {
+    event.waitUntil(
+        caches.keys()
+            .then((keys) => Promise.all(
+                keys.filter((key) => key !== CACHE).map((key) => caches.delete(key)),
+            ))
+            .then(() => self.clients.claim()),
+    );
+});
+
+// Each file is cached on its own, so one that's missing doesn't stop the rest being cached
+// and the worker installing.
+function cacheShell(cache) {
+    return Promise.all(SHELL.map((url) => cache.add(url).catch((err) => {
+        console.warn(`can't cache ${url}`, err);
+    })));
+}
+
+// Only what's needed to play offline is cached: the shell, and games as they're opened,
+// since the game page needs its settings in the query. Other pages show data that's stale
+// as soon as it's cached, or that belongs to whoever is logged in.
+function isCacheable(url) {
+    return url.origin === self.location.origin
+        && (SHELL.includes(url.pathname)
+            || url.pathname === "/game"
+            || url.pathname.startsWith("/pkg/"));
+}
+
+// Pages and assets come from the network when it's there, so updates show up right away,
+// and from the cache when it isn't.
+self.addEventListener("fetch", (event) => {
+    const request = event.request;
+    const url = new URL(request.url);
+    if (request.method !== "GET" || !isCacheable(url)) {
+        return;
+    }
+
+    event.respondWith(
+        fetch(request)
+            .then((response) => {
+                if (response.ok) {
+                    const copy = response.clone();
+                    caches.open(CACHE).then((cache) => cache.put(request, copy));
+                }
+                return response;
+            })
+            .catch(async () => {
+                const cached = await caches.match(request);
+                if (cached) {
+                    return cached;
+                }
+                // pages that weren't visited online start from the homepage, which can
+                // start any game
+                if (request.mode === "navigate") {
+                    return Response.redirect("/", 302);
+                }
+                return Response.error();
+            }),
+    );
+});
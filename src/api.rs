@@ -0,0 +1,363 @@
+use axum::{
+    body::StreamBody,
+    extract::{rejection::QueryRejection, FromRef, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use futures::{channel::mpsc, SinkExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    game_settings::{Difficulty, Size},
+    pages::scores::{Period, Score, ScoreMode},
+    score_store::{ScoreQuery, ScoreRecord, SharedScoreStore, MAX_PAGE},
+};
+
+const OPENAPI: &str = include_str!("../openapi.json");
+pub(crate) const MAX_PER_PAGE: i64 = 100;
+const EXPORT_BATCH: i64 = 500;
+
+/// The public JSON API, to be nested under `/api/v1`. It describes itself at `openapi.json`.
+pub fn routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    SharedScoreStore: FromRef<S>,
+    Config: FromRef<S>,
+{
+    Router::new()
+        .route("/scores", get(scores))
+        .route("/export", get(export))
+        .route("/openapi.json", get(openapi))
+}
+
+#[derive(Serialize)]
+struct ApiError {
+    error: String,
+}
+
+impl ApiError {
+    fn response(status: StatusCode, error: impl ToString) -> Response {
+        let error = error.to_string();
+        (status, Json(ApiError { error })).into_response()
+    }
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+#[derive(Deserialize)]
+struct ScoresParams {
+    difficulty: Difficulty,
+    size: Size,
+    #[serde(default)]
+    period: Period,
+    #[serde(default)]
+    mode: ScoreMode,
+    user: Option<String>,
+    #[serde(default = "first_page")]
+    page: i64,
+    per_page: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct ScoresResponse {
+    difficulty: Difficulty,
+    size: Size,
+    period: Period,
+    mode: ScoreMode,
+    page: i64,
+    per_page: i64,
+    total: i64,
+    scores: Vec<Score>,
+}
+
+async fn scores(
+    State(store): State<SharedScoreStore>,
+    State(config): State<Config>,
+    params: Result<Query<ScoresParams>, QueryRejection>,
+) -> Response {
+    let Query(params) = match params {
+        Ok(params) => params,
+        Err(rejection) => {
+            return ApiError::response(StatusCode::BAD_REQUEST, rejection.body_text())
+        }
+    };
+
+    let per_page = params.per_page.unwrap_or(config.leaderboard_size);
+    if !(1..=MAX_PAGE).contains(&params.page) || !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return ApiError::response(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "page must be between 1 and {MAX_PAGE} and per_page between 1 and {MAX_PER_PAGE}"
+            ),
+        );
+    }
+
+    let query = ScoreQuery {
+        difficulty: params.difficulty,
+        size: params.size,
+        period: params.period,
+        mode: params.mode,
+        search: String::new(),
+        username: params.user,
+        page: params.page,
+        per_page,
+    };
+
+    match store.fetch_scores(&query).await {
+        Ok(score_page) => Json(ScoresResponse {
+            difficulty: query.difficulty,
+            size: query.size,
+            period: query.period,
+            mode: query.mode,
+            page: query.page,
+            per_page,
+            total: score_page.total,
+            scores: score_page.scores,
+        })
+        .into_response(),
+
+        Err(err) => {
+            log::error!("failed to fetch scores: {err}");
+            ApiError::response(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+        }
+    }
+}
+
+async fn openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI)
+}
+
+#[derive(Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Deserialize)]
+struct ExportParams {
+    format: ExportFormat,
+    difficulty: Option<Difficulty>,
+    size: Option<Size>,
+    #[serde(default)]
+    period: Period,
+    #[serde(default)]
+    mode: ScoreMode,
+    #[serde(default)]
+    search: String,
+}
+
+trait ExportRow: Serialize {
+    const CSV_HEADER: &'static str;
+
+    fn csv_fields(&self) -> Vec<String>;
+}
+
+impl ExportRow for Score {
+    const CSV_HEADER: &'static str = "rank,username,time_in_seconds";
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.rank.to_string(),
+            self.username.clone(),
+            self.time_in_seconds.to_string(),
+        ]
+    }
+}
+
+impl ExportRow for ScoreRecord {
+    const CSV_HEADER: &'static str = "id,username,time_in_seconds,difficulty,size,created_at";
+
+    fn csv_fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.username.clone(),
+            self.time_in_seconds.to_string(),
+            self.difficulty.clone(),
+            self.size.clone(),
+            self.created_at.clone(),
+        ]
+    }
+}
+
+fn csv_field(field: String) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+type ExportSender = mpsc::Sender<Result<String, sqlx::Error>>;
+
+/// Writes rows into the response body as they're read. Sending fails once the client has
+/// disconnected, which stops the export.
+struct Exporter {
+    format: ExportFormat,
+    tx: ExportSender,
+    first: bool,
+}
+
+impl Exporter {
+    async fn start<R: ExportRow>(format: ExportFormat, mut tx: ExportSender) -> Option<Self> {
+        let opening = match format {
+            ExportFormat::Csv => format!("{}\n", R::CSV_HEADER),
+            ExportFormat::Json => "[".into(),
+        };
+        tx.send(Ok(opening)).await.ok()?;
+
+        Some(Self {
+            format,
+            tx,
+            first: true,
+        })
+    }
+
+    async fn write(&mut self, row: &impl ExportRow) -> Option<()> {
+        let line = match self.format {
+            ExportFormat::Csv => {
+                let fields = row.csv_fields().into_iter().map(csv_field);
+                format!("{}\n", fields.collect::<Vec<_>>().join(","))
+            }
+            ExportFormat::Json => {
+                let separator = if self.first { "" } else { "," };
+                let row = serde_json::to_string(row).expect("rows serialize to json");
+                format!("{separator}\n{row}")
+            }
+        };
+        self.first = false;
+
+        self.tx.send(Ok(line)).await.ok()
+    }
+
+    async fn finish(mut self, result: Result<(), sqlx::Error>) {
+        let ending = match (result, self.format) {
+            (Err(err), _) => Err(err),
+            (Ok(()), ExportFormat::Csv) => return,
+            (Ok(()), ExportFormat::Json) => Ok("\n]\n".into()),
+        };
+        let _ = self.tx.send(ending).await;
+    }
+}
+
+async fn export_board(
+    store: SharedScoreStore,
+    mut query: ScoreQuery,
+    format: ExportFormat,
+    tx: ExportSender,
+) {
+    let Some(mut exporter) = Exporter::start::<Score>(format, tx).await else {
+        return;
+    };
+
+    let result = async {
+        loop {
+            let score_page = store.fetch_scores(&query).await?;
+
+            for score in &score_page.scores {
+                if exporter.write(score).await.is_none() {
+                    return Ok(());
+                }
+            }
+            if (score_page.scores.len() as i64) < query.per_page {
+                return Ok(());
+            }
+
+            query.page += 1;
+        }
+    }
+    .await;
+
+    exporter.finish(result).await;
+}
+
+async fn export_table(store: SharedScoreStore, format: ExportFormat, tx: ExportSender) {
+    let Some(mut exporter) = Exporter::start::<ScoreRecord>(format, tx).await else {
+        return;
+    };
+
+    let result = async {
+        let mut records = store.score_records();
+
+        while let Some(record) = records.try_next().await? {
+            if exporter.write(&record).await.is_none() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+    .await;
+
+    exporter.finish(result).await;
+}
+
+/// Streams a scoreboard, or the whole `scores` table if no board is given, as CSV or JSON.
+async fn export(
+    State(store): State<SharedScoreStore>,
+    params: Result<Query<ExportParams>, QueryRejection>,
+) -> Response {
+    let Query(params) = match params {
+        Ok(params) => params,
+        Err(rejection) => {
+            return ApiError::response(StatusCode::BAD_REQUEST, rejection.body_text())
+        }
+    };
+
+    let (tx, rx) = mpsc::channel(16);
+    let name = match (params.difficulty, params.size) {
+        (Some(difficulty), Some(size)) => {
+            let query = ScoreQuery {
+                difficulty,
+                size,
+                period: params.period,
+                mode: params.mode,
+                search: params.search,
+                username: None,
+                page: 1,
+                per_page: EXPORT_BATCH,
+            };
+            tokio::spawn(export_board(store, query, params.format, tx));
+
+            format!(
+                "scores-{difficulty}-{size}-{}-{}",
+                params.period, params.mode
+            )
+        }
+
+        (None, None) => {
+            tokio::spawn(export_table(store, params.format, tx));
+
+            "scores".into()
+        }
+
+        _ => {
+            return ApiError::response(
+                StatusCode::BAD_REQUEST,
+                "difficulty and size must be given together",
+            )
+        }
+    };
+
+    let (content_type, extension) = match params.format {
+        ExportFormat::Csv => ("text/csv", "csv"),
+        ExportFormat::Json => ("application/json", "json"),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{name}.{extension}\""),
+            ),
+        ],
+        StreamBody::new(rx),
+    )
+        .into_response()
+}
@@ -3,8 +3,13 @@ use leptos_meta::*;
 use leptos_router::*;
 
 use crate::app_error::AppError;
-use crate::game_settings::{apply_setting, fetch_setting, Theme, Username};
-use crate::pages::{Error, Game, HomePage, Scores};
+use crate::game_settings::{
+    apply_setting, fetch_setting, system_prefers_dark, watch_system_theme, CustomTheme, Palette,
+    Theme, Username,
+};
+use crate::i18n::I18n;
+use crate::pages::{Account, Admin, Error, Game, HomePage, Scores, Stats};
+use crate::score_queue;
 
 const LIGHTBULB_SVG: &str = include_str!("../svgs/lightbulb.svg");
 const MOON_SVG: &str = include_str!("../svgs/moon.svg");
@@ -13,29 +18,61 @@ const MOON_SVG: &str = include_str!("../svgs/moon.svg");
 pub fn App() -> impl IntoView {
     provide_meta_context();
 
-    let theme_setting = fetch_setting::<Theme>("theme");
-    let (theme, set_theme) = create_signal(theme_setting.unwrap_or_default());
-    if theme_setting.is_none() {
-        Effect::new(move |_| {
-            if let Ok(Some(mql)) = leptos::window().match_media("(prefers-color-scheme: dark)") {
-                if mql.matches() {
-                    set_theme(Theme::Dark);
-                }
-            }
-        });
-    }
+    let (theme, set_theme) = create_signal(fetch_setting::<Theme>("theme").unwrap_or_default());
+    provide_context(theme);
+    provide_context(set_theme);
+    let (system_dark, set_system_dark) = create_signal(system_prefers_dark());
+    Effect::new(move |_| watch_system_theme(set_system_dark));
+    let dark = move || theme().is_dark(system_dark());
+
+    let (palette, set_palette) =
+        create_signal(fetch_setting::<Palette>("palette").unwrap_or_default());
+    provide_context(palette);
+    provide_context(set_palette);
+    let (custom_theme, set_custom_theme) =
+        create_signal(fetch_setting::<CustomTheme>("custom_theme").unwrap_or_default());
+    provide_context(custom_theme);
+    provide_context(set_custom_theme);
 
     let (username, set_username) = create_signal(Username::from(fetch_setting("username")));
     provide_context(username);
     provide_context(set_username);
 
+    let i18n = I18n::new();
+    provide_context(i18n);
+
+    // caches the app so it can be installed and played offline
+    Effect::new(|_| {
+        let _ = leptos::window()
+            .navigator()
+            .service_worker()
+            .register("/sw.js");
+    });
+
+    // scores won while the server couldn't be reached
+    Effect::new(|_| spawn_local(score_queue::send_queued()));
+    window_event_listener(ev::online, |_| spawn_local(score_queue::send_queued()));
+
     view! {
         <Stylesheet id="leptos" href="/pkg/tailwind.css" />
         <Stylesheet id="leptos" href="/pkg/rustsweeper.css" />
 
         <Title text="Rustsweeper" />
+        <Link rel="manifest" href="/manifest.webmanifest" />
+        <Link rel="icon" href="/icon.svg" type_="image/svg+xml" />
+        <Meta name="theme-color" content="#0284c7" />
 
-        <Html class=move || theme().to_string() />
+        <Html
+            lang=move || i18n.locale().to_string()
+            class=move || {
+                let mode = if dark() { "dark" } else { "light" };
+                format!("{mode} palette-{}", palette())
+            }
+        />
+        <Show when=move || palette() == Palette::Custom fallback=|| ()>
+            // set as HTML, as hydration markers among the rules would break them
+            <style inner_html=move || custom_theme.with(CustomTheme::css) />
+        </Show>
 
         <Router fallback=|| {
             let mut outside_errors = Errors::default();
@@ -51,22 +88,22 @@ pub fn App() -> impl IntoView {
                     class="theme-toggle"
 
                     on:click=move |_| {
-                        let new_theme = theme().toggle();
+                        let new_theme = if dark() { Theme::Light } else { Theme::Dark };
                         set_theme(new_theme);
                         apply_setting("theme", &new_theme);
                     }
 
                     inner_html=move || {
-                        match theme() {
-                            Theme::Light => MOON_SVG,
-                            Theme::Dark => LIGHTBULB_SVG,
-                        }
+                        if dark() { LIGHTBULB_SVG } else { MOON_SVG }
                     }
                 />
                 <Routes>
                     <Route path="" view=HomePage />
                     <Route path="game" view=Game />
                     <Route path="scores" view=Scores />
+                    <Route path="account" view=Account />
+                    <Route path="stats/:username" view=Stats />
+                    <Route path="admin" view=Admin />
                 </Routes>
             </main>
         </Router>
@@ -1,20 +1,151 @@
 use http::status::StatusCode;
+use leptos::ServerFnError;
 use leptos_router::ParamsError;
 use thiserror::Error;
 
+use crate::{
+    game_settings::UsernameError,
+    pages::{account::MIN_PASSWORD_LEN, scores::MAX_TIME_IN_SECONDS},
+    utils::to_time,
+};
+
+/// Starts the messages of errors sent by [`AppError::server_error`].
+const CODE_PREFIX: &str = "error:";
+
 #[derive(Clone, Debug, Error)]
 pub enum AppError {
     #[error("Not Found")]
     NotFound,
     #[error("Error reading new game settings: {0}")]
     ParamsError(#[from] ParamsError),
+    #[error(transparent)]
+    Validation(#[from] UsernameError),
+    #[error("That page of the scoreboard doesn't exist")]
+    PageOutOfRange,
+    #[error("Too many requests, wait a minute and try again")]
+    RateLimited,
+    #[error("Scores must take from 00:01 to {}", to_time(MAX_TIME_IN_SECONDS))]
+    InvalidTime,
+    #[error("The server had a problem with the database")]
+    Database,
+    #[error("The server can't be reached")]
+    Unavailable,
+    #[error("Password must be at least {} characters", MIN_PASSWORD_LEN)]
+    PasswordTooShort,
+    #[error("Incorrect name or password")]
+    LoginFailed,
+    #[error("Admin login required")]
+    AdminRequired,
+    #[error("The admin console is disabled")]
+    AdminDisabled,
 }
 
 impl AppError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             AppError::NotFound => StatusCode::NOT_FOUND,
-            AppError::ParamsError(_) => StatusCode::BAD_REQUEST,
+            AppError::ParamsError(_)
+            | AppError::Validation(_)
+            | AppError::PageOutOfRange
+            | AppError::InvalidTime
+            | AppError::PasswordTooShort => StatusCode::BAD_REQUEST,
+            AppError::LoginFailed | AppError::AdminRequired => StatusCode::UNAUTHORIZED,
+            AppError::AdminDisabled => StatusCode::FORBIDDEN,
+            AppError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Database => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// A stable name for the error, which server functions send to clients in place of its
+    /// message.
+    fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound => "not_found",
+            AppError::ParamsError(_) => "params",
+            AppError::Validation(error) => error.code(),
+            AppError::PageOutOfRange => "page_out_of_range",
+            AppError::RateLimited => "rate_limited",
+            AppError::InvalidTime => "invalid_time",
+            AppError::Database => "database",
+            AppError::Unavailable => "unavailable",
+            AppError::PasswordTooShort => "password_too_short",
+            AppError::LoginFailed => "login_failed",
+            AppError::AdminRequired => "admin_required",
+            AppError::AdminDisabled => "admin_disabled",
         }
     }
+
+    /// The error a server function returns for `error`, carrying its code.
+    pub fn server_error(error: impl Into<AppError>) -> ServerFnError {
+        ServerFnError::ServerError(format!("{CODE_PREFIX}{}", error.into().code()))
+    }
+
+    /// The error a server function sent with [`AppError::server_error`], if it was one of
+    /// those. Parameter errors are never sent, so they aren't recognised.
+    pub fn decode(error: &ServerFnError) -> Option<Self> {
+        let ServerFnError::ServerError(message) = error else {
+            return None;
+        };
+        let code = message.strip_prefix(CODE_PREFIX)?;
+
+        [
+            AppError::NotFound,
+            AppError::PageOutOfRange,
+            AppError::RateLimited,
+            AppError::InvalidTime,
+            AppError::Database,
+            AppError::Unavailable,
+            AppError::PasswordTooShort,
+            AppError::LoginFailed,
+            AppError::AdminRequired,
+            AppError::AdminDisabled,
+        ]
+        .into_iter()
+        .chain(UsernameError::ALL.map(AppError::Validation))
+        .find(|error| error.code() == code)
+    }
+
+    /// Works out what went wrong with a server function call from the error the client got.
+    /// Server errors that aren't recognised are taken to be database failures.
+    pub fn from_server_error(error: &ServerFnError) -> Self {
+        if let Some(error) = Self::decode(error) {
+            return error;
+        }
+
+        match error {
+            // the request never got an answer
+            ServerFnError::Request(_) => AppError::Unavailable,
+            // errors from proxies in front of the server start with their status
+            ServerFnError::ServerError(message) => {
+                let proxy_status = message
+                    .split(' ')
+                    .next()
+                    .and_then(|status| status.parse::<u16>().ok());
+                match proxy_status {
+                    Some(502..=504) => AppError::Unavailable,
+                    Some(429) => AppError::RateLimited,
+                    _ => AppError::Database,
+                }
+            }
+            _ => AppError::Database,
+        }
+    }
+
+    /// Whether trying the same call again later might work.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AppError::RateLimited | AppError::Database | AppError::Unavailable
+        )
+    }
+}
+
+/// Database errors are logged on the server, and clients are only told that one happened.
+#[cfg(feature = "ssr")]
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        tracing::error!("database error: {err}");
+        AppError::Database
+    }
 }
@@ -0,0 +1,255 @@
+//! Maintenance commands for the scoreboard, run against the database the server is
+//! configured with.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use futures::TryStreamExt;
+use sqlx::AnyPool;
+
+use rustsweeper::{
+    config::{Config, ConfigError, Settings},
+    database,
+    game_settings::{Difficulty, NameBlocklist, Size, Username, UsernameError},
+    pages::{
+        admin::{audit, is_banned, remove_score},
+        scores::{Period, ScoreMode, MAX_SCORES},
+    },
+    score_store::{ScoreQuery, ScoreRecord, ScoreStore},
+    utils::to_time,
+};
+
+/// Recorded as the admin in the audit log for changes made here.
+const CLI_ADMIN: &str = "cli";
+/// Tables whose rows belong to a player by name. Bans aren't moved, they stay with the name.
+const PLAYER_TABLES: [&str; 4] = ["scores", "games", "users", "name_claims"];
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(about = "Maintains the Rustsweeper scoreboard database")]
+struct Cli {
+    #[command(flatten)]
+    settings: Settings,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Applies any pending migrations
+    Migrate,
+    /// Lists the top scores on a board
+    Top {
+        difficulty: Difficulty,
+        size: Size,
+        #[arg(long, default_value_t)]
+        period: Period,
+        #[arg(long, default_value_t)]
+        mode: ScoreMode,
+        #[arg(long, default_value_t = MAX_SCORES as i64)]
+        limit: i64,
+    },
+    /// Deletes scores by id, as listed by the admin console or an export
+    Delete {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+    /// Moves a player's scores, games, account and name claim to an unused name
+    Rename { from: String, to: String },
+    /// Writes every score as JSON, to a file or stdout
+    Export { file: Option<PathBuf> },
+    /// Adds the scores from a JSON export, ignoring their ids
+    Import { file: PathBuf },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    _ = dotenvy::dotenv();
+    let cli = Cli::parse();
+
+    match run(cli.settings, cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(settings: Settings, command: Command) -> CliResult {
+    let config = Config::load(settings)?;
+    let url = config.database_url.ok_or(ConfigError::MissingDatabaseUrl)?;
+    let pool = database::connect(&url).await?;
+
+    match command {
+        Command::Migrate => {
+            database::migrate(&pool).await?;
+            println!("database migrated");
+        }
+
+        Command::Top {
+            difficulty,
+            size,
+            period,
+            mode,
+            limit,
+        } => {
+            let query = ScoreQuery {
+                difficulty,
+                size,
+                period,
+                mode,
+                search: String::new(),
+                username: None,
+                page: 1,
+                per_page: limit,
+            };
+
+            for score in pool.fetch_scores(&query).await?.scores {
+                println!(
+                    "{:>4}  {:<10}  {}",
+                    score.rank,
+                    score.username,
+                    to_time(score.time_in_seconds)
+                );
+            }
+        }
+
+        Command::Delete { ids } => {
+            for id in ids {
+                if remove_score(&pool, &pool, CLI_ADMIN, id).await? {
+                    println!("deleted score {id}");
+                } else {
+                    eprintln!("no score with id {id}");
+                }
+            }
+        }
+
+        Command::Rename { from, to } => {
+            let blocklist = NameBlocklist::load(&config.name_blocklist).map_err(|err| {
+                format!(
+                    "can't read the name blocklist {}: {err}",
+                    config.name_blocklist.display()
+                )
+            })?;
+            rename(&pool, &blocklist, &from, &to).await?
+        }
+
+        Command::Export { file } => match file {
+            Some(path) => export(&pool, BufWriter::new(File::create(path)?)).await?,
+            None => export(&pool, io::stdout().lock()).await?,
+        },
+
+        Command::Import { file } => import(&pool, File::open(file)?).await?,
+    }
+
+    Ok(())
+}
+
+async fn rename(pool: &AnyPool, blocklist: &NameBlocklist, from: &str, to: &str) -> CliResult {
+    Username::validate(to)?;
+    blocklist.check(to)?;
+    if is_banned(pool, to).await? {
+        return Err(UsernameError::Banned.into());
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // moving onto a name in use would merge two players' scores, or clash with an account
+    for table in PLAYER_TABLES {
+        let taken = sqlx::query_scalar::<_, String>(&format!(
+            "SELECT username FROM {table} WHERE username=$1 LIMIT 1"
+        ))
+        .bind(to)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+
+        if taken {
+            return Err(format!("{to} is already used by another player").into());
+        }
+    }
+
+    let mut scores = 0;
+    for table in PLAYER_TABLES {
+        let renamed = sqlx::query(&format!("UPDATE {table} SET username=$1 WHERE username=$2"))
+            .bind(to)
+            .bind(from)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+        if table == "scores" {
+            scores = renamed;
+        }
+    }
+    tx.commit().await?;
+
+    audit(pool, CLI_ADMIN, "rename", &format!("{from} to {to}")).await?;
+    println!("renamed {from} to {to}, moving {scores} scores");
+
+    Ok(())
+}
+
+/// Writes the same JSON as `/api/v1/export?format=json` with no board given.
+async fn export(pool: &AnyPool, mut out: impl Write) -> CliResult {
+    let mut records = pool.score_records();
+
+    write!(out, "[")?;
+    let mut separator = "";
+    while let Some(record) = records.try_next().await? {
+        write!(out, "{separator}\n{}", serde_json::to_string(&record)?)?;
+        separator = ",";
+    }
+    writeln!(out, "\n]")?;
+
+    out.flush().map_err(Into::into)
+}
+
+async fn import(pool: &AnyPool, file: File) -> CliResult {
+    let records: Vec<ScoreRecord> = serde_json::from_reader(BufReader::new(file))?;
+
+    let mut tx = pool.begin().await?;
+    for record in &records {
+        let (Ok(difficulty), Ok(size)) = (
+            record.difficulty.parse::<Difficulty>(),
+            record.size.parse::<Size>(),
+        ) else {
+            return Err(format!("score {} has an unknown board", record.id).into());
+        };
+        let (difficulty, size) = (difficulty.to_string(), size.to_string());
+
+        sqlx::query(
+            "
+                INSERT INTO scores(username, time_in_seconds, difficulty, size, created_at)
+                VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(&record.username)
+        .bind(record.time_in_seconds)
+        .bind(difficulty)
+        .bind(size)
+        .bind(&record.created_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    audit(
+        pool,
+        CLI_ADMIN,
+        "import",
+        &format!("{} scores", records.len()),
+    )
+    .await?;
+    println!("imported {} scores", records.len());
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! Server settings, read from a TOML file, then the environment (including .env), then
+//! command-line flags, with each overriding the one before.
+
+use std::{
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing_subscriber::{filter::ParseError, EnvFilter};
+
+use crate::{api::MAX_PER_PAGE, pages::scores::MAX_SCORES};
+
+/// Read when no other file is named, if it exists.
+const DEFAULT_CONFIG_FILE: &str = "rustsweeper.toml";
+
+/// Where scoreboards are kept.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    #[default]
+    Database,
+    /// Lost when the server stops, which suits demos.
+    Memory,
+}
+
+/// How log lines are written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// The settings one source gives. Anything it leaves unset falls through to the next.
+#[derive(Args, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// TOML file to read settings from [default: rustsweeper.toml, if it exists]
+    #[arg(long, env = "RUSTSWEEPER_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+    /// Address to serve on, instead of the Leptos site address
+    #[arg(long, env = "BIND_ADDRESS")]
+    pub bind_address: Option<SocketAddr>,
+    /// sqlite:// or postgres:// URL of the database, which may be left out when SCORE_STORE is memory
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+    /// A level like warn, or filter directives like info,sqlx=warn [default: info]
+    #[arg(long, env = "LOG_LEVEL")]
+    pub log_level: Option<String>,
+    /// How log lines are written [default: text]
+    #[arg(long, env = "LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    /// Scores shown on each page of a scoreboard [default: 10]
+    #[arg(long, env = "LEADERBOARD_SIZE")]
+    pub leaderboard_size: Option<i64>,
+    /// File of words that can't appear in player names [default: blocklist.txt]
+    #[arg(long, env = "NAME_BLOCKLIST")]
+    pub name_blocklist: Option<PathBuf>,
+    /// Where scoreboards are kept [default: database]
+    #[arg(long, env = "SCORE_STORE")]
+    pub score_store: Option<StoreKind>,
+    /// Whether to serve the admin console, which also needs ADMIN_USERNAME and ADMIN_PASSWORD [default: true]
+    #[arg(long, env = "ADMIN_CONSOLE")]
+    pub admin_console: Option<bool>,
+    /// Whether to serve Prometheus metrics from /metrics, which anyone who can reach the server may read [default: false]
+    #[arg(long, env = "METRICS")]
+    pub metrics: Option<bool>,
+    /// Server function calls allowed from one IP address each minute, or 0 for no limit [default: 120]
+    #[arg(long, env = "REQUESTS_PER_MINUTE")]
+    pub requests_per_minute: Option<u32>,
+    /// Scores one IP address, or one name, may post each minute, or 0 for no limit [default: 10]
+    #[arg(long, env = "SCORES_PER_MINUTE")]
+    pub scores_per_minute: Option<u32>,
+    /// Largest server function request body accepted, in bytes [default: 16384]
+    #[arg(long, env = "MAX_REQUEST_BYTES")]
+    pub max_request_bytes: Option<usize>,
+    /// Whether login and name claim cookies are only sent over HTTPS. Turn it off to log in over plain HTTP in development [default: true]
+    #[arg(long, env = "SECURE_COOKIES")]
+    pub secure_cookies: Option<bool>,
+    /// Whether to take clients' addresses from X-Forwarded-For, which only a single reverse proxy in front of the server should set [default: false]
+    #[arg(long, env = "TRUST_FORWARDED_FOR")]
+    pub trust_forwarded_for: Option<bool>,
+}
+
+impl Settings {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    /// Takes each setting from `self`, or from `fallback` where `self` leaves it unset.
+    fn or(self, fallback: Self) -> Self {
+        Self {
+            config: self.config.or(fallback.config),
+            bind_address: self.bind_address.or(fallback.bind_address),
+            database_url: self.database_url.or(fallback.database_url),
+            log_level: self.log_level.or(fallback.log_level),
+            log_format: self.log_format.or(fallback.log_format),
+            leaderboard_size: self.leaderboard_size.or(fallback.leaderboard_size),
+            name_blocklist: self.name_blocklist.or(fallback.name_blocklist),
+            score_store: self.score_store.or(fallback.score_store),
+            admin_console: self.admin_console.or(fallback.admin_console),
+            metrics: self.metrics.or(fallback.metrics),
+            requests_per_minute: self.requests_per_minute.or(fallback.requests_per_minute),
+            scores_per_minute: self.scores_per_minute.or(fallback.scores_per_minute),
+            max_request_bytes: self.max_request_bytes.or(fallback.max_request_bytes),
+            secure_cookies: self.secure_cookies.or(fallback.secure_cookies),
+            trust_forwarded_for: self.trust_forwarded_for.or(fallback.trust_forwarded_for),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("can't read config file {}: {source}", .path.display())]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid config file {}: {source}", .path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("database_url isn't set, give it in the config file, as DATABASE_URL or with --database-url")]
+    MissingDatabaseUrl,
+    #[error("log_level {log_level} isn't a valid filter: {source}")]
+    LogLevel {
+        log_level: String,
+        source: ParseError,
+    },
+    #[error("leaderboard_size is {0}, but must be between 1 and {MAX_PER_PAGE}")]
+    LeaderboardSize(i64),
+}
+
+/// The server's settings, once every source has been read.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub bind_address: Option<SocketAddr>,
+    /// Only missing with the memory store, when the server keeps accounts in memory too.
+    pub database_url: Option<String>,
+    /// Checked to be valid filter directives.
+    pub log_level: String,
+    pub log_format: LogFormat,
+    pub leaderboard_size: i64,
+    pub name_blocklist: PathBuf,
+    pub score_store: StoreKind,
+    pub admin_console: bool,
+    pub metrics: bool,
+    pub requests_per_minute: u32,
+    pub scores_per_minute: u32,
+    pub max_request_bytes: usize,
+    pub secure_cookies: bool,
+    pub trust_forwarded_for: bool,
+}
+
+impl Config {
+    /// Completes the settings given by flags and the environment with those from the
+    /// config file they name, or rustsweeper.toml.
+    pub fn load(flags: Settings) -> Result<Self, ConfigError> {
+        let file = match &flags.config {
+            Some(path) => Settings::read(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Settings::read(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => Settings::default(),
+        };
+        let settings = flags.or(file);
+
+        let leaderboard_size = settings.leaderboard_size.unwrap_or(MAX_SCORES as i64);
+        if !(1..=MAX_PER_PAGE).contains(&leaderboard_size) {
+            return Err(ConfigError::LeaderboardSize(leaderboard_size));
+        }
+
+        let log_level = settings.log_level.unwrap_or_else(|| "info".into());
+        if let Err(source) = EnvFilter::try_new(&log_level) {
+            return Err(ConfigError::LogLevel { log_level, source });
+        }
+
+        let score_store = settings.score_store.unwrap_or_default();
+        if settings.database_url.is_none() && score_store == StoreKind::Database {
+            return Err(ConfigError::MissingDatabaseUrl);
+        }
+
+        Ok(Self {
+            bind_address: settings.bind_address,
+            database_url: settings.database_url,
+            log_level,
+            log_format: settings.log_format.unwrap_or_default(),
+            leaderboard_size,
+            name_blocklist: settings
+                .name_blocklist
+                .unwrap_or_else(|| "blocklist.txt".into()),
+            score_store,
+            admin_console: settings.admin_console.unwrap_or(true),
+            metrics: settings.metrics.unwrap_or(false),
+            requests_per_minute: settings.requests_per_minute.unwrap_or(120),
+            scores_per_minute: settings.scores_per_minute.unwrap_or(10),
+            max_request_bytes: settings.max_request_bytes.unwrap_or(16 * 1024),
+            secure_cookies: settings.secure_cookies.unwrap_or(true),
+            trust_forwarded_for: settings.trust_forwarded_for.unwrap_or(false),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads `flags` over a config file holding `toml`, named after the test so tests
+    /// running at once don't share one.
+    fn load(name: &str, toml: &str, flags: Settings) -> Result<Config, ConfigError> {
+        let path = std::env::temp_dir().join(format!("{name}-{}.toml", std::process::id()));
+        fs::write(&path, toml).unwrap();
+        let config = Config::load(Settings {
+            config: Some(path.clone()),
+            ..flags
+        });
+        fs::remove_file(path).unwrap();
+
+        config
+    }
+
+    const DATABASE: &str = "database_url = \"sqlite://scores.db\"";
+
+    #[test]
+    fn unset_settings_take_their_defaults() {
+        let config = load("defaults", DATABASE, Settings::default()).unwrap();
+
+        assert_eq!(config.database_url.as_deref(), Some("sqlite://scores.db"));
+        assert_eq!(config.leaderboard_size, MAX_SCORES as i64);
+        assert_eq!(config.log_level, "info");
+        assert_eq!(config.score_store, StoreKind::Database);
+    }
+
+    #[test]
+    fn flags_override_the_file() {
+        let flags = Settings {
+            leaderboard_size: Some(5),
+            ..Settings::default()
+        };
+        let config = load(
+            "overrides",
+            &format!("{DATABASE}\nleaderboard_size = 20"),
+            flags,
+        );
+
+        assert_eq!(config.unwrap().leaderboard_size, 5);
+    }
+
+    #[test]
+    fn leaderboard_sizes_must_fit_a_page() {
+        for size in [0, -1, MAX_PER_PAGE + 1] {
+            let toml = format!("{DATABASE}\nleaderboard_size = {size}");
+
+            assert!(matches!(
+                load("leaderboard_size", &toml, Settings::default()),
+                Err(ConfigError::LeaderboardSize(got)) if got == size
+            ));
+        }
+    }
+
+    #[test]
+    fn log_levels_must_be_filters() {
+        let toml = format!("{DATABASE}\nlog_level = \"sqlx=loud\"");
+
+        assert!(matches!(
+            load("log_level", &toml, Settings::default()),
+            Err(ConfigError::LogLevel { .. })
+        ));
+    }
+
+    #[test]
+    fn only_the_memory_store_runs_without_a_database() {
+        assert!(matches!(
+            load("no_database", "", Settings::default()),
+            Err(ConfigError::MissingDatabaseUrl)
+        ));
+
+        let config = load("memory", "score_store = \"memory\"", Settings::default()).unwrap();
+        assert_eq!(config.database_url, None);
+    }
+
+    #[test]
+    fn unknown_and_unreadable_files_are_errors() {
+        assert!(matches!(
+            load("unknown", "leaderbord_size = 5", Settings::default()),
+            Err(ConfigError::Parse { .. })
+        ));
+
+        let missing = Settings {
+            config: Some("no-such-rustsweeper.toml".into()),
+            ..Settings::default()
+        };
+        assert!(matches!(
+            Config::load(missing),
+            Err(ConfigError::Read { .. })
+        ));
+    }
+}
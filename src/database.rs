@@ -0,0 +1,64 @@
+//! Connecting to sqlite or PostgreSQL, picked by the scheme of DATABASE_URL. Queries are
+//! written once for both, so they number their parameters (`$1`), which sqlite accepts too,
+//! and stick to functions that both databases have.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use sqlx::{
+    any::{install_default_drivers, AnyPoolOptions},
+    migrate::{MigrateError, Migrator},
+    AnyPool,
+};
+
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Connects to sqlite or PostgreSQL, whichever the URL's scheme names.
+pub async fn connect(url: &str) -> Result<AnyPool, sqlx::Error> {
+    install_default_drivers();
+    AnyPool::connect(url).await
+}
+
+/// Connects to a new, empty sqlite database kept in memory. The pool holds on to a
+/// connection for as long as it's open, as the database is gone once the last one closes.
+pub async fn connect_in_memory() -> Result<AnyPool, sqlx::Error> {
+    static DATABASES: AtomicUsize = AtomicUsize::new(0);
+    let database = DATABASES.fetch_add(1, Ordering::Relaxed);
+
+    install_default_drivers();
+    AnyPoolOptions::new()
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect(&format!("sqlite:file:rustsweeper-{database}?mode=memory"))
+        .await
+}
+
+/// Applies the migrations written for the pool's database.
+pub async fn migrate(pool: &AnyPool) -> Result<(), MigrateError> {
+    let migrator = match pool.connect_options().database_url.scheme() {
+        "postgres" | "postgresql" => &POSTGRES_MIGRATIONS,
+        _ => &SQLITE_MIGRATIONS,
+    };
+
+    migrator.run(pool).await
+}
+
+/// A `LIKE` pattern matching anything containing `search`, for use with `ESCAPE '\'`.
+/// Both sides should be lowercased, as only sqlite's `LIKE` ignores case.
+pub fn contains_pattern(search: &str) -> String {
+    let search = search
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{search}%")
+}
+
+/// A time formatted like `CURRENT_TIMESTAMP`, so it compares with the database's own
+/// timestamps as text.
+pub fn timestamp(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
@@ -0,0 +1,88 @@
+//! Games finished in this browser, kept in local storage so personal bests and recent
+//! games can be shown without the server.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, Utc};
+use gloo_storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::game_settings::{Difficulty, Outcome, Size};
+
+const HISTORY_KEY: &str = "game_history";
+/// Games kept, after which the oldest are forgotten.
+const MAX_GAMES: usize = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayedGame {
+    pub outcome: Outcome,
+    pub time_in_seconds: i64,
+    pub difficulty: Difficulty,
+    pub size: Size,
+    pub finished_at: DateTime<Utc>,
+    /// Whether it was won faster than any game on its board before it.
+    pub personal_best: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct GameHistory {
+    /// Newest first.
+    games: Vec<PlayedGame>,
+    /// The fastest win on each board, by `{difficulty}-{size}`, kept after its game is
+    /// forgotten.
+    best_times: HashMap<String, i64>,
+}
+
+impl PlayedGame {
+    /// When the game finished, in the player's time zone.
+    pub fn finished_at_local(&self) -> String {
+        self.finished_at
+            .with_timezone(&Local)
+            .format("%b %-d, %H:%M")
+            .to_string()
+    }
+}
+
+fn history() -> GameHistory {
+    LocalStorage::get(HISTORY_KEY).unwrap_or_default()
+}
+
+/// Every game kept, newest first.
+pub fn played_games() -> Vec<PlayedGame> {
+    history().games
+}
+
+/// Adds a finished game, and says whether it was a new personal best. The first game won
+/// on a board doesn't count as one.
+pub fn record(outcome: Outcome, time_in_seconds: i64, difficulty: Difficulty, size: Size) -> bool {
+    let mut history = history();
+
+    let mut personal_best = false;
+    if outcome == Outcome::Won {
+        let best = history
+            .best_times
+            .entry(format!("{difficulty}-{size}"))
+            .or_insert(time_in_seconds);
+        personal_best = time_in_seconds < *best;
+        *best = time_in_seconds.min(*best);
+    }
+
+    history.games.insert(
+        0,
+        PlayedGame {
+            outcome,
+            time_in_seconds,
+            difficulty,
+            size,
+            finished_at: Utc::now(),
+            personal_best,
+        },
+    );
+    history.games.truncate(MAX_GAMES);
+
+    if let Err(err) = LocalStorage::set(HISTORY_KEY, &history) {
+        log::warn!("couldn't save game history: {err}");
+    }
+
+    personal_best
+}
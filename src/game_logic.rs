@@ -7,8 +7,14 @@ use rand::{seq::SliceRandom, Rng};
 use thiserror::Error;
 
 use crate::{
-    game_settings::{Difficulty, ParseDifficultyError, ParseSizeError, Size, Username},
-    pages::scores::PostScore,
+    game_history,
+    game_settings::{Difficulty, Outcome, ParseDifficultyError, ParseSizeError, Size, Username},
+    i18n::use_i18n,
+    pages::{
+        scores::PostScore,
+        stats::{record_game, report_game_started},
+    },
+    score_queue,
     utils::to_time,
 };
 
@@ -59,17 +65,22 @@ pub struct GameInfo {
     cleared: isize,
     clear_total: isize,
     status: GameStatus,
+    /// Whether a won game beat the fastest this browser had won on its board.
+    new_best: bool,
 }
 
 impl GameInfo {
     pub fn to_view(&self) -> impl IntoView {
         let get_username = move || (expect_context::<ReadSignal<Username>>())().name;
+        let i18n = use_i18n();
         let time = to_time(self.elapsed_seconds);
 
         match self.status {
             GameStatus::Started => {
+                let (cleared, total) = (self.cleared, self.clear_total);
+
                 view! {
-                    {format!("{} cleared out of {}", self.cleared, self.clear_total)}
+                    {move || i18n.t_with("game.cleared", &[("cleared", &cleared), ("total", &total)])}
                     <br />
                     {time}
                     <br />
@@ -79,21 +90,23 @@ impl GameInfo {
             }
             GameStatus::GameOver => {
                 view! {
-                    {move || format!("Game over, {} 😭", get_username())}
+                    {move || i18n.t_with("game.over", &[("name", &get_username())])}
                     <br />
-                    "Time - " {time}
+                    {move || i18n.t_with("game.time", &[("time", &time)])}
                     <br />
                     ""
                     <br />
                 }
             }
             GameStatus::Victory => {
+                let new_best = self.new_best;
+
                 view! {
-                    {move || format!("You won, {}! 🥳", get_username())}
+                    {move || i18n.t_with("game.won", &[("name", &get_username())])}
                     <br />
-                    "Time - " {time}
+                    {move || i18n.t_with("game.time", &[("time", &time)])}
                     <br />
-                    ""
+                    {move || new_best.then(|| i18n.t("game.new_best"))}
                     <br />
                 }
             }
@@ -169,6 +182,8 @@ pub struct GameState {
     new_game_enabled: ReadSignal<bool>,
     set_new_game_enabled: WriteSignal<bool>,
     timer: Action<(), ()>,
+    post_score: Action<PostScore, Result<(), ServerFnError>>,
+    username: ReadSignal<Username>,
 }
 
 impl GameState {
@@ -220,6 +235,7 @@ impl GameState {
         });
 
         let (new_game_enabled, set_new_game_enabled) = create_signal(true);
+        let post_score = create_action(|score: &PostScore| score_queue::send(score.clone()));
 
         Self {
             params,
@@ -234,6 +250,8 @@ impl GameState {
             new_game_enabled,
             set_new_game_enabled,
             timer,
+            post_score,
+            username: expect_context(),
         }
     }
 
@@ -249,9 +267,18 @@ impl GameState {
         self.new_game_enabled
     }
 
+    pub fn post_score_action(&self) -> Action<PostScore, Result<(), ServerFnError>> {
+        self.post_score
+    }
+
     fn start(&mut self, row: isize, column: isize) {
         self.timer.dispatch(());
 
+        let GameParams { difficulty, size } = self.params;
+        spawn_local(async move {
+            let _ = report_game_started(difficulty, size).await;
+        });
+
         let mut rng = rand::thread_rng();
 
         let exclude = Vec::from_iter(std::iter::once((0, 0)).chain(ADJACENTS).filter_map(
@@ -341,18 +368,22 @@ impl GameState {
                     }
                 }
 
-                let post_score = create_server_action::<PostScore>();
+                let new_best = self.record_locally(Outcome::Won);
+                self.set_info.update(|info| info.new_best = new_best);
 
-                post_score.dispatch(PostScore {
-                    username: (expect_context::<ReadSignal<Username>>())().name,
+                self.post_score.dispatch(PostScore {
+                    username: self.username.get_untracked().name,
                     time_in_seconds: self.info.with(|info| info.elapsed_seconds),
                     difficulty: self.params.difficulty,
                     size: self.params.size,
+                    game_id: score_queue::new_game_id(),
                 });
             }
 
             GameStatus::GameOver => {
                 (self.set_new_game_enabled)(false);
+                self.record_locally(Outcome::Lost);
+                self.record_game(Outcome::Lost);
 
                 let mut mine_signals = self
                     .cell_states
@@ -494,7 +525,41 @@ impl GameState {
         cell_state.signal.expect("signal registered")((cell_state.interaction, cell_state.kind));
     }
 
+    /// Records the game as abandoned if it's still being played.
+    pub fn abandon(&self) {
+        if matches!(self.status, GameStatus::Started) {
+            self.record_game(Outcome::Abandoned);
+        }
+    }
+
+    fn record_game(&self, outcome: Outcome) {
+        // the game may be abandoned while its page is being torn down, so avoid actions
+        // tied to it and tolerate disposed signals
+        let (Some(username), Some(time_in_seconds)) = (
+            self.username
+                .try_with_untracked(|username| username.name.clone()),
+            self.info.try_with_untracked(|info| info.elapsed_seconds),
+        ) else {
+            return;
+        };
+        let GameParams { difficulty, size } = self.params;
+
+        spawn_local(async move {
+            let _ = record_game(username, outcome, time_in_seconds, difficulty, size).await;
+        });
+    }
+
+    /// Adds the game to this browser's history, saying whether it's a new personal best.
+    fn record_locally(&self, outcome: Outcome) -> bool {
+        let GameParams { difficulty, size } = self.params;
+        let time_in_seconds = self.info.with_untracked(|info| info.elapsed_seconds);
+
+        game_history::record(outcome, time_in_seconds, difficulty, size)
+    }
+
     pub fn reset(&mut self) {
+        self.abandon();
+
         self.status = Default::default();
         self.cleared = Default::default();
 
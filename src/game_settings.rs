@@ -1,4 +1,5 @@
 use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use cfg_if::cfg_if;
@@ -7,18 +8,24 @@ use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Copy, Clone, Default, Debug)]
+/// Light or dark mode. `System` follows the OS's setting, changing along with it.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub enum Theme {
     #[default]
+    System,
     Light,
     Dark,
 }
 
 impl Theme {
-    pub fn toggle(self) -> Self {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    /// Whether the page is dark, given whether the OS prefers dark.
+    pub fn is_dark(self, system_dark: bool) -> bool {
         match self {
-            Self::Dark => Self::Light,
-            Self::Light => Self::Dark,
+            Self::System => system_dark,
+            Self::Light => false,
+            Self::Dark => true,
         }
     }
 }
@@ -29,6 +36,7 @@ impl Display for Theme {
             f,
             "{}",
             match self {
+                Self::System => "system",
                 Self::Dark => "dark",
                 Self::Light => "light",
             }
@@ -41,6 +49,7 @@ impl FromStr for Theme {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "system" => Ok(Self::System),
             "dark" => Ok(Self::Dark),
             "light" => Ok(Self::Light),
             _ => Err(()),
@@ -48,6 +57,197 @@ impl FromStr for Theme {
     }
 }
 
+/// The colors of the game board, in light and dark themes alike.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub enum Palette {
+    #[default]
+    Classic,
+    Ocean,
+    HighContrast,
+    Colorblind,
+    /// The player's own colors, from their `CustomTheme`.
+    Custom,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 5] = [
+        Palette::Classic,
+        Palette::Ocean,
+        Palette::HighContrast,
+        Palette::Colorblind,
+        Palette::Custom,
+    ];
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Classic => "classic",
+                Self::Ocean => "ocean",
+                Self::HighContrast => "high-contrast",
+                Self::Colorblind => "colorblind",
+                Self::Custom => "custom",
+            }
+        )
+    }
+}
+
+impl FromStr for Palette {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Self::Classic),
+            "ocean" => Ok(Self::Ocean),
+            "high-contrast" => Ok(Self::HighContrast),
+            "colorblind" => Ok(Self::Colorblind),
+            "custom" => Ok(Self::Custom),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The CSS variables a custom theme sets, with the classic palette's light colors.
+pub const THEME_COLORS: [(&str, &str); 13] = [
+    ("board", "#9ca3af"),
+    ("cleared", "#e5e7eb"),
+    ("grid", "#030712"),
+    ("mine", "#000000"),
+    ("flag", "#1f2937"),
+    ("num-1", "#2563eb"),
+    ("num-2", "#16a34a"),
+    ("num-3", "#dc2626"),
+    ("num-4", "#9333ea"),
+    ("num-5", "#d97706"),
+    ("num-6", "#0891b2"),
+    ("num-7", "#db2777"),
+    ("num-8", "#475569"),
+];
+
+/// A color for each of `THEME_COLORS`, as `#rrggbb`. It's kept in a cookie as the colors'
+/// hex digits separated by dots, which cookie encoding leaves alone.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomTheme(Vec<String>);
+
+impl CustomTheme {
+    pub fn color(&self, index: usize) -> &str {
+        &self.0[index]
+    }
+
+    /// Sets a color, if it's written as `#rrggbb`.
+    pub fn set_color(&mut self, index: usize, color: &str) {
+        if let Some(color) = parse_color(color) {
+            self.0[index] = color;
+        }
+    }
+
+    /// A stylesheet setting the theme's variables when the custom palette is picked.
+    pub fn css(&self) -> String {
+        let variables = THEME_COLORS
+            .iter()
+            .zip(&self.0)
+            .map(|((variable, _), color)| format!("--{variable}: {color};"))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(":root.palette-custom {{ {variables} }}")
+    }
+}
+
+impl Default for CustomTheme {
+    fn default() -> Self {
+        Self(
+            THEME_COLORS
+                .iter()
+                .map(|(_, color)| color.to_string())
+                .collect(),
+        )
+    }
+}
+
+fn parse_color(color: &str) -> Option<String> {
+    let hex = color.strip_prefix('#')?;
+    (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| format!("#{}", hex.to_ascii_lowercase()))
+}
+
+impl Display for CustomTheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let hex = self
+            .0
+            .iter()
+            .map(|color| color.trim_start_matches('#'))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", hex.join("."))
+    }
+}
+
+impl FromStr for CustomTheme {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let colors = s
+            .split('.')
+            .map(|hex| parse_color(&format!("#{hex}")))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(())?;
+
+        if colors.len() == THEME_COLORS.len() {
+            Ok(Self(colors))
+        } else {
+            Err(())
+        }
+    }
+}
+
+pub const USERNAME_BOUNDS: RangeInclusive<usize> = 3..=10;
+
+pub fn valid_chars(username: &str) -> bool {
+    username
+        .chars()
+        .all(|c| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// Reasons the server refuses a name.
+#[derive(Error, Copy, Clone, Debug, PartialEq)]
+pub enum UsernameError {
+    #[error("Name must be 3-10 alphanumeric characters and underscores")]
+    Invalid,
+    #[error("That name isn't allowed")]
+    Blocked,
+    #[error("That name belongs to an account, log in to post scores with it")]
+    Registered,
+    #[error("That name has been claimed by another player")]
+    Claimed,
+    #[error("That name has been banned")]
+    Banned,
+}
+
+impl UsernameError {
+    pub const ALL: [Self; 5] = [
+        Self::Invalid,
+        Self::Blocked,
+        Self::Registered,
+        Self::Claimed,
+        Self::Banned,
+    ];
+
+    /// A stable name for the error, which servers send to clients in place of its message.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::Invalid => "name_invalid",
+            Self::Blocked => "name_blocked",
+            Self::Registered => "name_registered",
+            Self::Claimed => "name_claimed",
+            Self::Banned => "name_banned",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Username {
     pub name: String,
@@ -71,6 +271,14 @@ impl Username {
             stable: true,
         }
     }
+
+    pub fn validate(name: &str) -> Result<(), UsernameError> {
+        if USERNAME_BOUNDS.contains(&name.len()) && valid_chars(name) {
+            Ok(())
+        } else {
+            Err(UsernameError::Invalid)
+        }
+    }
 }
 
 impl From<Option<String>> for Username {
@@ -150,8 +358,59 @@ impl Display for Size {
     }
 }
 
+/// How a game ended.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Won,
+    Lost,
+    Abandoned,
+}
+
+impl FromStr for Outcome {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
 cfg_if! { if #[cfg(feature = "ssr")] {
 
+/// Words that may not appear in names, read from a file with one word per line.
+/// Matching ignores case and underscores, so "Bad_Word" is caught by "badword".
+#[derive(Clone, Debug)]
+pub struct NameBlocklist(std::sync::Arc<[String]>);
+
+impl NameBlocklist {
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let words = std::fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|word| word.to_ascii_lowercase().replace('_', ""))
+            .collect();
+
+        Ok(Self(words))
+    }
+
+    pub fn check(&self, name: &str) -> Result<(), UsernameError> {
+        let name = name.to_ascii_lowercase().replace('_', "");
+
+        if self.0.iter().any(|word| name.contains(word.as_str())) {
+            Err(UsernameError::Blocked)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 pub fn fetch_setting<T: FromStr + Default>(setting: &str) -> Option<T> {
     leptos::use_context().and_then(|leptos_axum::RequestParts { headers, ..}| {
         let jar = axum_extra::extract::CookieJar::from_headers(&headers);
@@ -163,6 +422,35 @@ pub fn apply_setting<T: ToString>(_setting: &str, _value: &T) {
     unimplemented!()
 }
 
+const COLOR_SCHEME_HINT: &str = "Sec-CH-Prefers-Color-Scheme";
+
+/// Read from the request's `Sec-CH-Prefers-Color-Scheme` client hint, taken to be light
+/// if the browser didn't send one. Browsers that support it are asked to send it, and to
+/// retry this request with it if they didn't. Pages only depend on it when the player's
+/// theme follows the system, so otherwise it isn't asked for and responses don't vary on it.
+pub fn system_prefers_dark() -> bool {
+    if fetch_setting::<Theme>("theme").unwrap_or_default() != Theme::System {
+        return false;
+    }
+
+    if let Some(response) = leptos::use_context::<leptos_axum::ResponseOptions>() {
+        let hint = http::HeaderValue::from_static(COLOR_SCHEME_HINT);
+        response.insert_header(http::HeaderName::from_static("accept-ch"), hint.clone());
+        response.insert_header(http::HeaderName::from_static("critical-ch"), hint.clone());
+        response.append_header(http::header::VARY, hint);
+    }
+
+    leptos::use_context().is_some_and(|leptos_axum::RequestParts { headers, .. }| {
+        headers
+            .get(COLOR_SCHEME_HINT)
+            .and_then(|hint| hint.to_str().ok())
+            .is_some_and(|hint| hint.trim_matches('"') == "dark")
+    })
+}
+
+/// Nothing to follow on the server.
+pub fn watch_system_theme(_on_change: impl Fn(bool) + 'static) {}
+
 } else if #[cfg(target_arch = "wasm32")] {
 
 pub fn fetch_setting<T: FromStr>(setting: &str) -> Option<T> {
@@ -177,6 +465,30 @@ pub fn apply_setting<T: ToString>(setting: &str, value: &T) {
             .expires_after(chrono::Duration::weeks(999).to_std().expect("convert to std duration")));
 }
 
+fn dark_query() -> Option<web_sys::MediaQueryList> {
+    leptos::window().match_media("(prefers-color-scheme: dark)").ok()?
+}
+
+pub fn system_prefers_dark() -> bool {
+    dark_query().is_some_and(|query| query.matches())
+}
+
+/// Calls `on_change` with whether the OS prefers dark whenever that changes.
+pub fn watch_system_theme(on_change: impl Fn(bool) + 'static) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    let Some(query) = dark_query() else {
+        return;
+    };
+    let listener = Closure::<dyn Fn()>::new({
+        let query = query.clone();
+        move || on_change(query.matches())
+    });
+    query.set_onchange(Some(listener.as_ref().unchecked_ref()));
+    // the app lasts as long as the page, so the listener does too
+    listener.forget();
+}
+
 } else {
 // stubs for rust-analyzer, shouldn't actually get called
 
@@ -188,4 +500,50 @@ pub fn apply_setting<T: ToString>(_setting: &str, _value: &T) {
     unimplemented!()
 }
 
+pub fn system_prefers_dark() -> bool {
+    false
+}
+
+pub fn watch_system_theme(_on_change: impl Fn(bool) + 'static) {}
+
 }}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn blocklist() -> NameBlocklist {
+        NameBlocklist::load(concat!(env!("CARGO_MANIFEST_DIR"), "/blocklist.txt"))
+            .expect("blocklist.txt can be read")
+    }
+
+    #[test]
+    fn blocked_words_are_caught_anywhere_in_names() {
+        for name in ["admin", "the_admin", "ADMIN_1", "ad_min", "Mod_Erator"] {
+            assert!(
+                matches!(blocklist().check(name), Err(UsernameError::Blocked)),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_names_are_allowed() {
+        for name in ["alice", "ad_in", "moderate"] {
+            assert!(blocklist().check(name).is_ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn listed_words_ignore_case_underscores_and_spaces() {
+        let path = std::env::temp_dir().join(format!("blocklist-{}.txt", std::process::id()));
+        std::fs::write(&path, "# comment\n\n  Bad_Word  \n").unwrap();
+        let blocklist = NameBlocklist::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(blocklist.check("a_BADWORD").is_err());
+        assert!(blocklist.check("bad_wo_rd").is_err());
+        assert!(blocklist.check("comment").is_ok());
+        assert!(blocklist.check("bad").is_ok());
+    }
+}
@@ -0,0 +1,64 @@
+//! Probes for process supervisors and load balancers.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode};
+use sqlx::AnyPool;
+use tracing::warn;
+
+/// How long the database has to answer a readiness check.
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Set once a shutdown signal arrives, so probes fail while requests in flight finish.
+#[derive(Clone, Debug, Default)]
+pub struct ShuttingDown(Arc<AtomicBool>);
+
+impl ShuttingDown {
+    pub fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether the server is running. It stops being healthy as soon as it starts shutting down.
+pub async fn healthz(State(shutting_down): State<ShuttingDown>) -> (StatusCode, &'static str) {
+    if shutting_down.get() {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting down")
+    } else {
+        (StatusCode::OK, "ok")
+    }
+}
+
+/// Whether the server can take requests, which needs the database to answer a query and
+/// the server not to be shutting down.
+pub async fn readyz(
+    State(shutting_down): State<ShuttingDown>,
+    State(pool): State<AnyPool>,
+) -> (StatusCode, &'static str) {
+    if shutting_down.get() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "shutting down");
+    }
+
+    let ping = sqlx::query("SELECT 1").execute(&pool);
+
+    match tokio::time::timeout(READY_TIMEOUT, ping).await {
+        Ok(Ok(_)) => (StatusCode::OK, "ok"),
+        Ok(Err(err)) => {
+            warn!("readiness check failed: {err}");
+            (StatusCode::SERVICE_UNAVAILABLE, "database unavailable")
+        }
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "database didn't answer in time",
+        ),
+    }
+}
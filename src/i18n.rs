@@ -0,0 +1,278 @@
+//! Translations of the UI. Each locale's strings come from `locales/{locale}.txt`, where
+//! every line is `key = text`. Callers fill in words in braces, like `{name}`, and keys
+//! missing from a locale fall back to English.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use cfg_if::cfg_if;
+use leptos::*;
+
+use crate::app_error::AppError;
+use crate::game_settings::{apply_setting, fetch_setting};
+use crate::pages::{account::MIN_PASSWORD_LEN, scores::MAX_TIME_IN_SECONDS};
+use crate::utils::{error_message, to_time};
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::En, Locale::Es];
+
+    /// The language's name in itself, for picking it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Es => "Español",
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Self::En => include_str!("../locales/en.txt"),
+            Self::Es => include_str!("../locales/es.txt"),
+        }
+    }
+
+    fn strings(self) -> &'static HashMap<&'static str, &'static str> {
+        static STRINGS: [OnceLock<HashMap<&str, &str>>; Locale::ALL.len()] =
+            [OnceLock::new(), OnceLock::new()];
+
+        STRINGS[self as usize].get_or_init(|| {
+            self.source()
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, text)| (key.trim(), text.trim()))
+                .collect()
+        })
+    }
+
+    /// The text for `key`, or the key itself if no locale has it.
+    pub fn translate(self, key: &str) -> String {
+        self.strings()
+            .get(key)
+            .or_else(|| Locale::En.strings().get(key))
+            .map_or(key, |text| *text)
+            .to_owned()
+    }
+
+    /// The text for `key`, with each `{name}` replaced by its value in `args`.
+    pub fn translate_with(self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(self.translate(key), |text, (name, value)| {
+                text.replace(&format!("{{{name}}}"), &value.to_string())
+            })
+    }
+
+    /// The best match for the languages in an `Accept-Language` header, if any are
+    /// supported.
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut languages = accept_language
+            .split(',')
+            .filter_map(|language| {
+                let mut parts = language.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |quality| quality.parse::<f32>().ok())?;
+
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect::<Vec<_>>();
+        // stable, so languages of equal quality keep the client's order
+        languages.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+        languages.into_iter().find_map(|(tag, _)| {
+            let primary = tag.split('-').next().unwrap_or_default();
+            primary.to_ascii_lowercase().parse().ok()
+        })
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::En => "en",
+                Self::Es => "es",
+            }
+        )
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The UI's locale, shared as a context by the app.
+#[derive(Copy, Clone)]
+pub struct I18n {
+    locale: ReadSignal<Locale>,
+    set_locale: WriteSignal<Locale>,
+}
+
+impl I18n {
+    /// Starts with the locale the player picked, or else the best match for their
+    /// browser's languages.
+    pub fn new() -> Self {
+        let locale = fetch_setting::<Locale>("locale")
+            .or_else(preferred_locale)
+            .unwrap_or_default();
+        let (locale, set_locale) = create_signal(locale);
+
+        Self { locale, set_locale }
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale.get()
+    }
+
+    /// Switches locale, remembering it for later visits.
+    pub fn set_locale(&self, locale: Locale) {
+        apply_setting("locale", &locale);
+        self.set_locale.set(locale);
+    }
+
+    /// The text for `key` in the current locale.
+    pub fn t(&self, key: &str) -> String {
+        self.locale.with(|locale| locale.translate(key))
+    }
+
+    /// The text for `key` in the current locale, with its `{name}`s filled in.
+    pub fn t_with(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        self.locale.with(|locale| locale.translate_with(key, args))
+    }
+
+    /// Describes an error in the current locale.
+    pub fn error(&self, error: &AppError) -> String {
+        match error {
+            AppError::NotFound => self.t("error.not_found"),
+            AppError::ParamsError(error) => self.t_with("error.params", &[("error", error)]),
+            AppError::Validation(error) => self.t(&format!("error.{}", error.code())),
+            AppError::PageOutOfRange => self.t("error.page_out_of_range"),
+            AppError::RateLimited => self.t("error.rate_limited"),
+            AppError::InvalidTime => self.t_with(
+                "error.invalid_time",
+                &[("max", &to_time(MAX_TIME_IN_SECONDS))],
+            ),
+            AppError::Database => self.t("error.database"),
+            AppError::Unavailable => self.t("error.unavailable"),
+            AppError::PasswordTooShort => {
+                self.t_with("error.password_too_short", &[("min", &MIN_PASSWORD_LEN)])
+            }
+            AppError::LoginFailed => self.t("error.login_failed"),
+            AppError::AdminRequired => self.t("error.admin_required"),
+            AppError::AdminDisabled => self.t("error.admin_disabled"),
+        }
+    }
+
+    /// Describes an error from a server function in the current locale. Errors the app
+    /// doesn't know of are shown as the server wrote them.
+    pub fn server_error(&self, error: ServerFnError) -> String {
+        match AppError::decode(&error) {
+            Some(error) => self.error(&error),
+            None => error_message(error),
+        }
+    }
+}
+
+impl Default for I18n {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn use_i18n() -> I18n {
+    expect_context()
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+
+/// Negotiated from the request's `Accept-Language` header.
+fn preferred_locale() -> Option<Locale> {
+    use_context::<leptos_axum::RequestParts>().and_then(|parts| {
+        let accept_language = parts.headers.get(http::header::ACCEPT_LANGUAGE)?;
+        Locale::negotiate(accept_language.to_str().ok()?)
+    })
+}
+
+} else if #[cfg(target_arch = "wasm32")] {
+
+/// The locale the server rendered the page in, so hydrating doesn't change it.
+fn preferred_locale() -> Option<Locale> {
+    document()
+        .document_element()?
+        .get_attribute("lang")?
+        .parse()
+        .ok()
+}
+
+} else {
+// stub for rust-analyzer, shouldn't actually get called
+
+fn preferred_locale() -> Option<Locale> {
+    None
+}
+
+}}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_picks_the_best_supported_language() {
+        assert_eq!(
+            Locale::negotiate("es-MX,es;q=0.9,en;q=0.8"),
+            Some(Locale::Es)
+        );
+        assert_eq!(
+            Locale::negotiate("fr-FR,fr;q=0.9,en;q=0.5"),
+            Some(Locale::En)
+        );
+        assert_eq!(Locale::negotiate("en;q=0.5, ES"), Some(Locale::Es));
+        assert_eq!(Locale::negotiate("en;q=0.8,es;q=0.8"), Some(Locale::En));
+        assert_eq!(Locale::negotiate("es;q=0,en;q=0.1"), Some(Locale::En));
+    }
+
+    #[test]
+    fn negotiate_skips_what_it_cant_use() {
+        assert_eq!(Locale::negotiate(""), None);
+        assert_eq!(Locale::negotiate("*"), None);
+        assert_eq!(Locale::negotiate("fr,de;q=0.7"), None);
+        assert_eq!(Locale::negotiate("es;q=high,en;q=0.1"), Some(Locale::En));
+    }
+
+    #[test]
+    fn every_locale_has_every_string() {
+        for locale in Locale::ALL {
+            let mut missing = Locale::En
+                .strings()
+                .keys()
+                .filter(|key| !locale.strings().contains_key(*key))
+                .collect::<Vec<_>>();
+            missing.sort();
+
+            assert!(missing.is_empty(), "{locale} is missing {missing:?}");
+        }
+    }
+}
@@ -1,9 +1,28 @@
 use cfg_if::cfg_if;
+#[cfg(feature = "ssr")]
+pub mod api;
 pub mod app;
 pub mod app_error;
+#[cfg(feature = "ssr")]
+pub mod config;
+#[cfg(feature = "ssr")]
+pub mod database;
+pub mod game_history;
 pub mod game_logic;
 pub mod game_settings;
+#[cfg(feature = "ssr")]
+pub mod health;
+pub mod i18n;
+#[cfg(feature = "ssr")]
+pub mod metrics;
 pub mod pages;
+#[cfg(feature = "ssr")]
+pub mod rate_limit;
+pub mod score_queue;
+#[cfg(feature = "ssr")]
+pub mod score_store;
+#[cfg(feature = "ssr")]
+pub mod telemetry;
 pub mod utils;
 
 cfg_if! { if #[cfg(feature = "hydrate")] {
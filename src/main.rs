@@ -2,86 +2,239 @@ cfg_if::cfg_if! { if #[cfg(feature = "ssr")] {
 use axum::{
     body::{boxed, Body, BoxBody},
     extract::{Path, State, RawQuery, FromRef},
-    http::{Request, Response, StatusCode, Uri, header::HeaderMap},
+    http::{Request, Response, StatusCode, Uri, header::HeaderMap, HeaderName},
+    middleware,
     response::IntoResponse,
     response::Response as AxumResponse,
     routing::get,
     Router,
 };
-use leptos::logging::*;
 use leptos::*;
 use leptos_axum::{generate_route_list, LeptosRoutes};
-use sqlx::SqlitePool;
-use tower::ServiceExt;
-use tower_http::services::ServeDir;
+use clap::Parser;
+use sqlx::AnyPool;
+use std::{error::Error, net::SocketAddr, process::ExitCode, sync::Arc};
+use tower::{ServiceBuilder, ServiceExt};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::{DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::{info, warn, Level, Span};
 
 use rustsweeper::app::App;
+use rustsweeper::config::{Config, Settings, StoreKind};
+use rustsweeper::game_settings::NameBlocklist;
+use rustsweeper::health::{healthz, readyz, ShuttingDown};
+use rustsweeper::metrics::{serve_metrics, time_server_fns, Metrics};
+use rustsweeper::pages::admin::AdminCredentials;
+use rustsweeper::rate_limit::{limit_server_fns, RateLimits};
+use rustsweeper::score_store::{MemoryScoreStore, SharedScoreStore};
+use rustsweeper::telemetry::{self, request_span, RequestSpan, REQUEST_ID_HEADER};
+
+#[derive(Parser)]
+#[command(about = "Serves Rustsweeper")]
+struct Cli {
+    #[command(flatten)]
+    settings: Settings,
+}
 
 #[derive(FromRef, Debug, Clone)]
 struct AppState {
     leptos_options: LeptosOptions,
-    db_pool: SqlitePool,
+    db_pool: AnyPool,
+    score_store: SharedScoreStore,
+    blocklist: NameBlocklist,
+    admin_credentials: Option<AdminCredentials>,
+    config: Config,
+    metrics: Metrics,
+    rate_limits: RateLimits,
+    shutting_down: ShuttingDown,
+}
+
+impl AppState {
+    /// Makes the state server functions need available to them.
+    fn provide_contexts(&self) {
+        provide_context(self.db_pool.clone());
+        provide_context(self.score_store.clone());
+        provide_context(self.blocklist.clone());
+        provide_context(self.admin_credentials.clone());
+        provide_context(self.config.clone());
+        provide_context(self.metrics.clone());
+        provide_context(self.rate_limits.clone());
+    }
 }
 
 #[tokio::main]
-async fn main() {
-    simple_logger::init_with_level(log::Level::Info).expect("logging initializes");
+async fn main() -> ExitCode {
+    // load .env first so its variables are seen as settings
+    _ = dotenvy::dotenv();
+    let cli = Cli::parse();
+
+    match serve(cli.settings).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn serve(settings: Settings) -> Result<(), Box<dyn Error>> {
+    let config = Config::load(settings)?;
+    telemetry::init(&config).map_err(|err| format!("can't start logging: {err}"))?;
 
-    let leptos_options = get_configuration(None)
+    let mut leptos_options = get_configuration(None)
         .await
-        .expect("configuration exists")
+        .map_err(|err| format!("can't read the Leptos configuration: {err}"))?
         .leptos_options;
+    if let Some(addr) = config.bind_address {
+        leptos_options.site_addr = addr;
+    }
     let addr = leptos_options.site_addr;
     let routes = generate_route_list(App);
-    let db_url = dotenvy::var("DATABASE_URL").expect(".env exists");
-    let db_pool = SqlitePool::connect(&db_url)
+    let db_pool = match &config.database_url {
+        Some(url) => rustsweeper::database::connect(url).await,
+        None => {
+            info!("no database_url, keeping accounts and game stats in memory");
+            rustsweeper::database::connect_in_memory().await
+        }
+    }
+    .map_err(|err| format!("can't connect to the database: {err}"))?;
+
+    rustsweeper::database::migrate(&db_pool)
         .await
-        .expect("sqlite ready for connections");
+        .map_err(|err| format!("can't migrate the database: {err}"))?;
+
+    let score_store: SharedScoreStore = match config.score_store {
+        StoreKind::Database => Arc::new(db_pool.clone()),
+        StoreKind::Memory => Arc::new(MemoryScoreStore::default()),
+    };
 
-    sqlx::migrate!("./migrations")
-        .run(&db_pool)
+    let blocklist = NameBlocklist::load(&config.name_blocklist).map_err(|err| {
+        format!("can't read the name blocklist {}: {err}", config.name_blocklist.display())
+    })?;
+    let admin_credentials = AdminCredentials::load(&db_pool, config.admin_console)
         .await
-        .expect("database migrated");
+        .map_err(|err| format!("can't set up the admin console: {err}"))?;
+    if config.admin_console && admin_credentials.is_none() {
+        warn!("ADMIN_USERNAME or ADMIN_PASSWORD unset, the admin console is disabled");
+    }
 
-    let state = {
-        let db_pool = db_pool.clone();
-        AppState {
-            leptos_options,
-            db_pool,
-        }
+    let state = AppState {
+        leptos_options,
+        db_pool,
+        score_store,
+        blocklist,
+        admin_credentials,
+        rate_limits: RateLimits::new(&config),
+        config,
+        metrics: Metrics::default(),
+        shutting_down: ShuttingDown::default(),
     };
+    let context_state = state.clone();
+    let db_pool = state.db_pool.clone();
+    let shutting_down = state.shutting_down.clone();
+    let request_id = HeaderName::from_static(REQUEST_ID_HEADER);
 
-    let app = Router::new()
+    let mut app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .nest("/api/v1", rustsweeper::api::routes())
         .route(
             "/api/*fn_name",
-            get(server_fn_handler).post(server_fn_handler),
-        )
-        .leptos_routes_with_context(&state, routes, move || {
-            provide_context(db_pool.clone());
-        }, App)
+            get(server_fn_handler)
+                .post(server_fn_handler)
+                .layer(middleware::from_fn_with_state(state.metrics.clone(), time_server_fns))
+                .layer(middleware::from_fn_with_state(state.rate_limits.clone(), limit_server_fns)),
+        );
+    if state.config.metrics {
+        app = app.route("/metrics", get(serve_metrics));
+    }
+
+    let app = app
+        .leptos_routes_with_context(&state, routes, move || context_state.provide_contexts(), App)
         .fallback(file_and_error_handler)
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::new(request_id.clone(), MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(
+                            DefaultOnResponse::new()
+                                .level(Level::INFO)
+                                .latency_unit(LatencyUnit::Millis),
+                        ),
+                )
+                .layer(PropagateRequestIdLayer::new(request_id)),
+        )
         .with_state(state);
 
-    log!("listening on http://{}", &addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await
-        .expect("axum server binds to addr");
+    let server = axum::Server::try_bind(&addr)
+        .map_err(|err| format!("can't listen on {addr}: {err}"))?;
+    info!("listening on http://{addr}");
+    server
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal(shutting_down))
+        .await?;
+
+    info!("requests drained, closing the database pool");
+    db_pool.close().await;
+
+    Ok(())
+}
+
+/// Resolves on Ctrl+C or SIGTERM, after which requests already being served are finished.
+/// Health probes fail from then on.
+async fn shutdown_signal(shutting_down: ShuttingDown) {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("can't listen for Ctrl+C: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!("can't listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+    shutting_down.set();
+    info!("shutting down");
 }
 
 async fn server_fn_handler(
-    State(db_pool): State<SqlitePool>,
+    State(state): State<AppState>,
     path: Path<String>,
     headers: HeaderMap,
     raw_query: RawQuery,
     request: Request<Body>,
 ) -> impl IntoResponse {
+    let request_span = RequestSpan(Span::current());
+
     leptos_axum::handle_server_fns_with_context(
         path,
         headers,
         raw_query,
         move || {
-            provide_context(db_pool.clone());
+            state.provide_contexts();
+            provide_context(request_span.clone());
         },
         request,
     )
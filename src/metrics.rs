@@ -0,0 +1,163 @@
+//! Counters and timings served from `/metrics` in the Prometheus text format.
+
+use std::time::Instant;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use leptos::leptos_server::server_fn_by_path;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use sqlx::AnyPool;
+
+use crate::game_settings::{Difficulty, Outcome, Size};
+
+/// The server's metrics. Clones share the same counters.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    registry: Registry,
+    games_started: IntCounterVec,
+    games_finished: IntCounterVec,
+    scores_posted: IntCounterVec,
+    server_fn_seconds: HistogramVec,
+    pool_connections: IntGauge,
+    pool_idle_connections: IntGauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry =
+            Registry::new_custom(Some("rustsweeper".into()), None).expect("metric prefix is valid");
+        let board = ["difficulty", "size"];
+
+        let games_started = IntCounterVec::new(
+            Opts::new(
+                "games_started_total",
+                "Games started, as reported by clients",
+            ),
+            &board,
+        )
+        .expect("metric is valid");
+        let games_finished = IntCounterVec::new(
+            Opts::new(
+                "games_finished_total",
+                "Games won, lost or abandoned, as reported by clients",
+            ),
+            &["outcome", "difficulty", "size"],
+        )
+        .expect("metric is valid");
+        let scores_posted = IntCounterVec::new(
+            Opts::new("scores_posted_total", "Scores added to the scoreboards"),
+            &board,
+        )
+        .expect("metric is valid");
+        let server_fn_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "server_fn_duration_seconds",
+                "Time taken to answer server function calls",
+            ),
+            &["function"],
+        )
+        .expect("metric is valid");
+        let pool_connections = IntGauge::new("db_pool_connections", "Open database connections")
+            .expect("metric is valid");
+        let pool_idle_connections = IntGauge::new(
+            "db_pool_idle_connections",
+            "Database connections not in use",
+        )
+        .expect("metric is valid");
+
+        for collector in [
+            Box::new(games_started.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(games_finished.clone()),
+            Box::new(scores_posted.clone()),
+            Box::new(server_fn_seconds.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pool_idle_connections.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            games_started,
+            games_finished,
+            scores_posted,
+            server_fn_seconds,
+            pool_connections,
+            pool_idle_connections,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn game_started(&self, difficulty: Difficulty, size: Size) {
+        self.games_started
+            .with_label_values(&[&difficulty.to_string(), &size.to_string()])
+            .inc();
+    }
+
+    pub fn game_finished(&self, outcome: Outcome, difficulty: Difficulty, size: Size) {
+        self.games_finished
+            .with_label_values(&[
+                &outcome.to_string(),
+                &difficulty.to_string(),
+                &size.to_string(),
+            ])
+            .inc();
+    }
+
+    pub fn score_posted(&self, difficulty: Difficulty, size: Size) {
+        self.scores_posted
+            .with_label_values(&[&difficulty.to_string(), &size.to_string()])
+            .inc();
+    }
+}
+
+/// Serves every metric, reading the pool's size as it goes.
+pub async fn serve_metrics(
+    State(metrics): State<Metrics>,
+    State(pool): State<AnyPool>,
+) -> Response {
+    metrics.pool_connections.set(pool.size().into());
+    metrics.pool_idle_connections.set(pool.num_idle() as i64);
+
+    let encoder = TextEncoder::new();
+    match encoder.encode_to_string(&metrics.registry.gather()) {
+        Ok(text) => ([(header::CONTENT_TYPE, encoder.format_type())], text).into_response(),
+        Err(err) => {
+            tracing::error!("failed to encode metrics: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Middleware for the server function route, timing each call.
+pub async fn time_server_fns(
+    State(metrics): State<Metrics>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    // server functions are served from `/api/{url}`
+    let path = request.uri().path().trim_start_matches("/api/").to_owned();
+    let started = Instant::now();
+    let response = next.run(request).await;
+
+    // only real functions are timed, so made up paths can't add series
+    if server_fn_by_path(&path).is_some() {
+        let function = path.trim_end_matches(|c: char| c.is_ascii_digit());
+        metrics
+            .server_fn_seconds
+            .with_label_values(&[function])
+            .observe(started.elapsed().as_secs_f64());
+    }
+
+    response
+}
@@ -9,3 +9,12 @@ pub use scores::Scores;
 
 pub mod error;
 pub use error::Error;
+
+pub mod account;
+pub use account::Account;
+
+pub mod stats;
+pub use stats::Stats;
+
+pub mod admin;
+pub use admin::Admin;
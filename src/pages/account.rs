@@ -0,0 +1,472 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::*;
+
+use crate::game_settings::{apply_setting, Username};
+use crate::i18n::use_i18n;
+
+pub const MIN_PASSWORD_LEN: usize = 8;
+
+#[cfg(feature = "ssr")]
+use crate::{
+    app_error::AppError,
+    game_settings::{NameBlocklist, UsernameError},
+};
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::Utc;
+use http::header::{HeaderValue, SET_COOKIE};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use sqlx::AnyPool;
+use std::sync::OnceLock;
+use time::Duration;
+
+use crate::config::Config;
+use crate::database::timestamp;
+use crate::pages::admin::is_banned;
+
+const SESSION_COOKIE: &str = "session";
+const CLAIM_COOKIE: &str = "claim_token";
+const TOKEN_LEN: usize = 32;
+/// How long a login lasts before the player has to log in again.
+pub const SESSION_LIFETIME: Duration = Duration::days(30);
+/// Claims never expire, so their cookies are kept for as long as browsers allow.
+const CLAIM_LIFETIME: Duration = Duration::days(400);
+
+pub fn request_cookie(name: &str) -> Option<String> {
+    use_context().and_then(|leptos_axum::RequestParts { headers, .. }| {
+        let jar = CookieJar::from_headers(&headers);
+        jar.get(name).map(|cookie| cookie.value().to_owned())
+    })
+}
+
+pub fn set_cookie(cookie: Cookie) {
+    if let Some(response) = use_context::<leptos_axum::ResponseOptions>() {
+        response.append_header(
+            SET_COOKIE,
+            HeaderValue::from_str(&cookie.to_string()).expect("cookie is a valid header"),
+        );
+    }
+}
+
+/// A cookie scripts can't read, sent only over HTTPS unless the server is configured
+/// otherwise.
+pub fn secret_cookie(name: &'static str, token: String, lifetime: Duration) -> Cookie<'static> {
+    let secure = use_context::<Config>().map_or(true, |config| config.secure_cookies);
+
+    Cookie::build(name, token)
+        .path("/")
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Lax)
+        .max_age(lifetime)
+        .finish()
+}
+
+/// When something lasting `lifetime` from now ends, formatted to compare with the
+/// database's timestamps.
+pub fn expiry(lifetime: Duration) -> String {
+    timestamp(Utc::now() + chrono::Duration::seconds(lifetime.whole_seconds()))
+}
+
+pub fn random_token() -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Hashes a password with a new salt, for [`verify_password`] to check later.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+}
+
+/// Whether `password` is the one `password_hash` was made from.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|password_hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok()
+    })
+}
+
+/// A hash of no account's password, to check logins for unknown names against.
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+    DUMMY_HASH.get_or_init(|| hash_password("").expect("the empty password can be hashed"))
+}
+
+/// Logs in with a new session, ending the one the request had, if any, along with every
+/// expired session.
+async fn start_session(pool: &AnyPool, user_id: i64) -> Result<(), ServerFnError> {
+    let token = random_token();
+
+    sqlx::query("DELETE FROM sessions WHERE token_hash=$1 OR expires_at<=$2")
+        .bind(hash_token(&request_cookie(SESSION_COOKIE).unwrap_or_default()))
+        .bind(timestamp(Utc::now()))
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT INTO sessions(token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(hash_token(&token))
+        .bind(user_id)
+        .bind(expiry(SESSION_LIFETIME))
+        .execute(pool)
+        .await?;
+
+    set_cookie(secret_cookie(SESSION_COOKIE, token, SESSION_LIFETIME));
+
+    Ok(())
+}
+
+/// The name of the account logged in with the request's session cookie, if any.
+pub async fn authenticated_user(pool: &AnyPool) -> Result<Option<String>, sqlx::Error> {
+    let Some(token) = request_cookie(SESSION_COOKIE) else {
+        return Ok(None);
+    };
+
+    sqlx::query_scalar(
+        "
+            SELECT users.username
+            FROM sessions
+            JOIN users ON users.id = sessions.user_id
+            WHERE sessions.token_hash=$1
+                AND sessions.expires_at>$2
+        ",
+    )
+    .bind(hash_token(&token))
+    .bind(timestamp(Utc::now()))
+    .fetch_optional(pool)
+    .await
+}
+
+/// Whether a name has been taken by a registered account.
+async fn is_registered(pool: &AnyPool, username: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE username=$1")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+        .map(|id| id.is_some())
+}
+
+/// Checks that a guest name is owned by this browser's claim token, or by nobody. With
+/// `claim`, an unowned name is claimed so that nobody else can post scores under it.
+async fn check_claim(pool: &AnyPool, username: &str, claim: bool) -> Result<(), AppError> {
+    let token = request_cookie(CLAIM_COOKIE);
+
+    let owner_hash: Option<String> =
+        sqlx::query_scalar("SELECT token_hash FROM name_claims WHERE username=$1")
+            .bind(username)
+            .fetch_optional(pool)
+            .await?;
+
+    match (owner_hash, token) {
+        (Some(owner_hash), Some(token)) if owner_hash == hash_token(&token) => Ok(()),
+
+        (Some(_), _) => Err(UsernameError::Claimed.into()),
+
+        (None, _) if !claim => Ok(()),
+
+        (None, token) => {
+            let token = token.unwrap_or_else(|| {
+                let token = random_token();
+                set_cookie(secret_cookie(CLAIM_COOKIE, token.clone(), CLAIM_LIFETIME));
+                token
+            });
+            let token_hash = hash_token(&token);
+
+            match sqlx::query("INSERT INTO name_claims(username, token_hash) VALUES ($1, $2)")
+                .bind(username)
+                .bind(token_hash)
+                .execute(pool)
+                .await
+            {
+                Ok(_) => Ok(()),
+                Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+                    Err(UsernameError::Claimed.into())
+                }
+                Err(err) => Err(err.into()),
+            }
+        }
+    }
+}
+
+/// Checks that a name is well formed, not blocklisted, and not owned by someone else, so
+/// that a guest can use it, claiming it with `claim`.
+async fn check_guest_name(pool: &AnyPool, username: &str, claim: bool) -> Result<(), AppError> {
+    Username::validate(username)?;
+    expect_context::<NameBlocklist>().check(username)?;
+
+    if is_registered(pool, username).await? {
+        return Err(UsernameError::Registered.into());
+    }
+
+    check_claim(pool, username, claim).await
+}
+
+/// The name to record scores under: the logged in account's, or else the guest's own name
+/// once it passes [`check_guest_name`], which claims it.
+pub async fn resolve_username(pool: &AnyPool, username: String) -> Result<String, AppError> {
+    resolve(pool, username, true).await
+}
+
+/// The name to record lost and abandoned games under, like [`resolve_username`] but
+/// without claiming guest names, so that reporting games can't take names from players.
+pub async fn resolve_player(pool: &AnyPool, username: String) -> Result<String, AppError> {
+    resolve(pool, username, false).await
+}
+
+async fn resolve(pool: &AnyPool, username: String, claim: bool) -> Result<String, AppError> {
+    let username = match authenticated_user(pool).await? {
+        Some(account_name) => account_name,
+        None => {
+            check_guest_name(pool, &username, claim).await?;
+            username
+        }
+    };
+
+    if is_banned(pool, &username).await? {
+        return Err(UsernameError::Banned.into());
+    }
+
+    Ok(username)
+}
+
+}}
+
+#[server(GetAccount)]
+async fn get_account() -> Result<Option<String>, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    authenticated_user(&pool)
+        .await
+        .map_err(AppError::server_error)
+}
+
+#[server(Register)]
+async fn register(username: String, password: String) -> Result<String, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    check_guest_name(&pool, &username, true)
+        .await
+        .map_err(AppError::server_error)?;
+    if password.len() < MIN_PASSWORD_LEN {
+        return Err(AppError::server_error(AppError::PasswordTooShort));
+    }
+
+    let password_hash =
+        hash_password(&password).map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+
+    let user_id = match sqlx::query_scalar(
+        "INSERT INTO users(username, password_hash) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(&username)
+    .bind(password_hash)
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return Err(AppError::server_error(UsernameError::Registered));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    start_session(&pool, user_id).await?;
+
+    Ok(username)
+}
+
+#[server(Login)]
+async fn login(username: String, password: String) -> Result<String, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let invalid = || AppError::server_error(AppError::LoginFailed);
+
+    let account: Option<(i64, String)> =
+        sqlx::query_as("SELECT id, password_hash FROM users WHERE username=$1")
+            .bind(&username)
+            .fetch_optional(&pool)
+            .await?;
+
+    // unknown names are still checked against a password hash, so they take as long to
+    // refuse as wrong passwords and don't give away which accounts exist
+    let password_hash = match &account {
+        Some((_, password_hash)) => password_hash,
+        None => dummy_hash(),
+    };
+    let verified = verify_password(&password, password_hash);
+    let Some((user_id, _)) = account.filter(|_| verified) else {
+        return Err(invalid());
+    };
+
+    start_session(&pool, user_id).await?;
+
+    Ok(username)
+}
+
+#[server(Logout)]
+async fn logout() -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    if let Some(token) = request_cookie(SESSION_COOKIE) {
+        sqlx::query("DELETE FROM sessions WHERE token_hash=$1")
+            .bind(hash_token(&token))
+            .execute(&pool)
+            .await?;
+    }
+
+    let mut cookie = Cookie::named(SESSION_COOKIE);
+    cookie.set_path("/");
+    cookie.make_removal();
+    set_cookie(cookie);
+
+    Ok(())
+}
+
+/// Renders the login and registration forms, or the logged in account.
+#[component]
+pub fn Account() -> impl IntoView {
+    let set_username = expect_context::<WriteSignal<Username>>();
+    let register = create_server_action::<Register>();
+    let login = create_server_action::<Login>();
+    let logout = create_server_action::<Logout>();
+    let i18n = use_i18n();
+
+    let account = create_resource(
+        move || (register.version()(), login.version()(), logout.version()()),
+        |_| get_account(),
+    );
+
+    for action in [register.value(), login.value()] {
+        create_effect(move |_| {
+            if let Some(Ok(name)) = action() {
+                apply_setting("username", &name);
+                set_username(Username::new(name));
+            }
+        });
+    }
+
+    let error = move || {
+        [register.value()(), login.value()()]
+            .into_iter()
+            .flatten()
+            .find_map(Result::err)
+            .map(|error| i18n.server_error(error))
+    };
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || match account() {
+                Some(Ok(Some(name))) => view! {
+                    <div class="panel">
+                        <div class="panel-label">{move || i18n.t("account.title")}</div>
+                        <div class="panel-table">
+                            {move || i18n.t_with("account.logged_in", &[("name", &name)])}
+                        </div>
+                    </div>
+
+                    <ActionForm action=logout>
+                        <div class="btns">
+                            <div class="btn">
+                                <input type="submit" value=move || i18n.t("account.log_out") />
+                            </div>
+                        </div>
+                    </ActionForm>
+                }
+                .into_view(),
+
+                _ => view! {
+                    <CredentialsForm label="account.log_in">
+                        <ActionForm action=login>
+                            <CredentialsFields />
+                            <div class="btns">
+                                <div class="btn">
+                                    <input type="submit" value=move || i18n.t("account.log_in") />
+                                </div>
+                            </div>
+                        </ActionForm>
+                    </CredentialsForm>
+
+                    <CredentialsForm label="account.register">
+                        <ActionForm action=register>
+                            <CredentialsFields />
+                            <div class="btns">
+                                <div class="btn">
+                                    <input type="submit" value=move || i18n.t("account.register") />
+                                </div>
+                            </div>
+                        </ActionForm>
+                    </CredentialsForm>
+
+                    <div class="account-error">
+                        {error}
+                    </div>
+                }
+                .into_view(),
+            }}
+        </Transition>
+
+        <div class="btns">
+            <div class="btn">
+                <A href="/">
+                    {move || i18n.t("common.return")}
+                </A>
+            </div>
+        </div>
+    }
+}
+
+/// A panel titled with the text for the `label` key.
+#[component]
+fn CredentialsForm(label: &'static str, children: Children) -> impl IntoView {
+    let i18n = use_i18n();
+
+    view! {
+        <div class="panel account-panel">
+            <div class="panel-label">{move || i18n.t(label)}</div>
+            {children()}
+        </div>
+    }
+}
+
+#[component]
+fn CredentialsFields() -> impl IntoView {
+    let i18n = use_i18n();
+
+    view! {
+        <table class="panel-table">
+            <tr class="panel-row">
+                <td class="panel-row-label">
+                    <label for="username">{move || i18n.t("account.name")}</label>
+                </td>
+                <td>
+                    <input type="text" name="username" size="12" />
+                </td>
+            </tr>
+            <tr class="panel-row">
+                <td class="panel-row-label">
+                    <label for="password">{move || i18n.t("account.password")}</label>
+                </td>
+                <td>
+                    <input type="password" name="password" size="12" />
+                </td>
+            </tr>
+        </table>
+    }
+}
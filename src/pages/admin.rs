@@ -0,0 +1,724 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::*;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::use_i18n;
+use crate::utils::to_time;
+
+const ADMIN_PAGE_SIZE: i64 = 25;
+#[cfg(feature = "ssr")]
+const AUDIT_LOG_LEN: i64 = 50;
+
+/// A score as the admin console lists it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    id: i64,
+    username: String,
+    time_in_seconds: i64,
+    difficulty: String,
+    size: String,
+    created_at: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScoreEntryPage {
+    entries: Vec<ScoreEntry>,
+    total: i64,
+}
+
+impl ScoreEntryPage {
+    fn page_count(&self) -> i64 {
+        ((self.total + ADMIN_PAGE_SIZE - 1) / ADMIN_PAGE_SIZE).max(1)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Ban {
+    username: String,
+    banned_by: String,
+    banned_at: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct AuditEntry {
+    admin: String,
+    action: String,
+    details: String,
+    created_at: String,
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+
+use axum_extra::extract::cookie::Cookie;
+use chrono::Utc;
+use sqlx::AnyPool;
+use time::Duration;
+
+use crate::app_error::AppError;
+use crate::database::timestamp;
+use crate::score_store::{ScoreRecord, ScoreStore, SharedScoreStore};
+
+use crate::pages::account::{
+    expiry, hash_password, hash_token, random_token, request_cookie, secret_cookie, set_cookie,
+    verify_password,
+};
+
+const ADMIN_COOKIE: &str = "admin_session";
+/// How long an admin login lasts.
+const ADMIN_SESSION_LIFETIME: Duration = Duration::hours(12);
+
+/// The admin login, read from the ADMIN_USERNAME and ADMIN_PASSWORD environment variables.
+/// The console is disabled if either is missing.
+#[derive(Clone, Debug)]
+pub struct AdminCredentials {
+    username: String,
+    password_hash: String,
+}
+
+impl AdminCredentials {
+    /// Reads the login if the console is `enabled`. When it isn't the login the console last
+    /// ran with, or the console is disabled, every admin session is ended.
+    pub async fn load(
+        pool: &AnyPool,
+        enabled: bool,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error + Send + Sync>> {
+        let login = enabled
+            .then(|| {
+                let username = dotenvy::var("ADMIN_USERNAME").ok()?;
+                let password = dotenvy::var("ADMIN_PASSWORD").ok()?;
+                (!username.is_empty() && !password.is_empty()).then_some((username, password))
+            })
+            .flatten();
+
+        let stored: Option<(String, String)> =
+            sqlx::query_as("SELECT username, password_hash FROM admin_credentials")
+                .fetch_optional(pool)
+                .await?;
+        if let (Some((username, password)), Some((stored_username, password_hash))) =
+            (&login, stored)
+        {
+            if *username == stored_username && verify_password(password, &password_hash) {
+                return Ok(Some(Self {
+                    username: username.clone(),
+                    password_hash,
+                }));
+            }
+        }
+
+        let credentials = match login {
+            Some((username, password)) => Some(Self {
+                username,
+                password_hash: hash_password(&password).map_err(|err| err.to_string())?,
+            }),
+            None => None,
+        };
+
+        let mut transaction = pool.begin().await?;
+        sqlx::query("DELETE FROM admin_sessions")
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("DELETE FROM admin_credentials")
+            .execute(&mut *transaction)
+            .await?;
+        if let Some(credentials) = &credentials {
+            sqlx::query("INSERT INTO admin_credentials(username, password_hash) VALUES ($1, $2)")
+                .bind(&credentials.username)
+                .bind(&credentials.password_hash)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(credentials)
+    }
+}
+
+/// Whether a name has been banned from posting scores.
+pub async fn is_banned(pool: &AnyPool, username: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT username FROM banned_names WHERE username=$1")
+        .bind(username)
+        .fetch_optional(pool)
+        .await
+        .map(|name| name.is_some())
+}
+
+/// The admin logged in with the request's admin session cookie, if any. Sessions only last
+/// while the console is enabled with the login they started with.
+async fn current_admin(pool: &AnyPool) -> Result<Option<String>, ServerFnError> {
+    let Some(credentials) = expect_context::<Option<AdminCredentials>>() else {
+        return Ok(None);
+    };
+    let Some(token) = request_cookie(ADMIN_COOKIE) else {
+        return Ok(None);
+    };
+    let token_hash = hash_token(&token);
+
+    sqlx::query_scalar(
+        "
+            SELECT admin
+            FROM admin_sessions
+            WHERE token_hash=$1
+                AND admin=$2
+                AND expires_at>$3
+        ",
+    )
+    .bind(token_hash)
+    .bind(credentials.username)
+    .bind(timestamp(Utc::now()))
+    .fetch_optional(pool)
+    .await
+    .map_err(AppError::server_error)
+}
+
+async fn require_admin(pool: &AnyPool) -> Result<String, ServerFnError> {
+    current_admin(pool)
+        .await?
+        .ok_or_else(|| AppError::server_error(AppError::AdminRequired))
+}
+
+/// Records a change to the scoreboard in the audit log.
+pub async fn audit(
+    pool: &AnyPool,
+    admin: &str,
+    action: &str,
+    details: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO audit_log(admin, action, details) VALUES ($1, $2, $3)")
+        .bind(admin)
+        .bind(action)
+        .bind(details)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+impl From<ScoreRecord> for ScoreEntry {
+    fn from(record: ScoreRecord) -> Self {
+        ScoreEntry {
+            id: record.id,
+            username: record.username,
+            time_in_seconds: record.time_in_seconds,
+            difficulty: record.difficulty,
+            size: record.size,
+            created_at: record.created_at,
+        }
+    }
+}
+
+/// Deletes a score from the store and records what it was in the audit log. Returns whether
+/// it existed.
+pub async fn remove_score(
+    pool: &AnyPool,
+    store: &dyn ScoreStore,
+    admin: &str,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let Some(score) = store.delete_score(id).await? else {
+        return Ok(false);
+    };
+
+    let details = format!(
+        "#{id} {} {} on {} {} at {}",
+        score.username,
+        to_time(score.time_in_seconds),
+        score.difficulty,
+        score.size,
+        score.created_at,
+    );
+    audit(pool, admin, "delete score", &details).await?;
+
+    Ok(true)
+}
+
+}}
+
+#[server(GetAdmin)]
+async fn get_admin() -> Result<Option<String>, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    current_admin(&pool).await
+}
+
+#[server(AdminLogin)]
+async fn admin_login(username: String, password: String) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let Some(credentials) = expect_context::<Option<AdminCredentials>>() else {
+        return Err(AppError::server_error(AppError::AdminDisabled));
+    };
+
+    // the password is checked even for a wrong name, so the time taken doesn't give the name away
+    let verified = verify_password(&password, &credentials.password_hash);
+    if username != credentials.username || !verified {
+        return Err(AppError::server_error(AppError::LoginFailed));
+    }
+
+    let token = random_token();
+    let token_hash = hash_token(&token);
+    sqlx::query("DELETE FROM admin_sessions WHERE expires_at<=$1")
+        .bind(timestamp(Utc::now()))
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO admin_sessions(token_hash, admin, expires_at) VALUES ($1, $2, $3)")
+        .bind(token_hash)
+        .bind(username)
+        .bind(expiry(ADMIN_SESSION_LIFETIME))
+        .execute(&pool)
+        .await?;
+
+    set_cookie(secret_cookie(ADMIN_COOKIE, token, ADMIN_SESSION_LIFETIME));
+
+    Ok(())
+}
+
+#[server(AdminLogout)]
+async fn admin_logout() -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    if let Some(token) = request_cookie(ADMIN_COOKIE) {
+        let token_hash = hash_token(&token);
+        sqlx::query("DELETE FROM admin_sessions WHERE token_hash=$1")
+            .bind(token_hash)
+            .execute(&pool)
+            .await?;
+    }
+
+    let mut cookie = Cookie::named(ADMIN_COOKIE);
+    cookie.set_path("/");
+    cookie.make_removal();
+    set_cookie(cookie);
+
+    Ok(())
+}
+
+/// Lists scores, newest first, whose names contain `search`.
+#[server(AdminScores)]
+async fn admin_scores(search: String, page: i64) -> Result<ScoreEntryPage, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    require_admin(&pool).await?;
+
+    let (records, total) = expect_context::<SharedScoreStore>()
+        .recent_records(&search, page, ADMIN_PAGE_SIZE)
+        .await?;
+    let entries = records.into_iter().map(ScoreEntry::from).collect();
+
+    Ok(ScoreEntryPage { entries, total })
+}
+
+#[server(DeleteScore)]
+async fn delete_score(id: i64) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let admin = require_admin(&pool).await?;
+    let store = expect_context::<SharedScoreStore>();
+
+    remove_score(&pool, store.as_ref(), &admin, id).await?;
+
+    Ok(())
+}
+
+/// Deletes every score posted under a name, returning how many were removed.
+#[server(DeleteScoresBy)]
+async fn delete_scores_by(username: String) -> Result<u64, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let admin = require_admin(&pool).await?;
+
+    let deleted = expect_context::<SharedScoreStore>()
+        .delete_scores_by(&username)
+        .await?;
+
+    let details = format!("{deleted} scores by {username}");
+    audit(&pool, &admin, "delete scores", &details).await?;
+
+    Ok(deleted)
+}
+
+#[server(BanName)]
+async fn ban_name(username: String) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let admin = require_admin(&pool).await?;
+
+    let banned = sqlx::query(
+        "
+            INSERT INTO banned_names(username, banned_by)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        ",
+    )
+    .bind(&username)
+    .bind(&admin)
+    .execute(&pool)
+    .await?
+    .rows_affected();
+
+    // names that were already banned are left as they were
+    if banned > 0 {
+        audit(&pool, &admin, "ban", &username).await?;
+    }
+
+    Ok(())
+}
+
+#[server(UnbanName)]
+async fn unban_name(username: String) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    let admin = require_admin(&pool).await?;
+
+    let unbanned = sqlx::query("DELETE FROM banned_names WHERE username=$1")
+        .bind(&username)
+        .execute(&pool)
+        .await?
+        .rows_affected();
+
+    if unbanned > 0 {
+        audit(&pool, &admin, "unban", &username).await?;
+    }
+
+    Ok(())
+}
+
+#[server(GetBans)]
+async fn get_bans() -> Result<Vec<Ban>, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    require_admin(&pool).await?;
+
+    sqlx::query_as("SELECT username, banned_by, banned_at FROM banned_names ORDER BY username")
+        .fetch_all(&pool)
+        .await
+        .map_err(Into::into)
+}
+
+#[server(GetAuditLog)]
+async fn get_audit_log() -> Result<Vec<AuditEntry>, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+    require_admin(&pool).await?;
+
+    sqlx::query_as(
+        "
+            SELECT admin, action, details, created_at
+            FROM audit_log
+            ORDER BY id DESC
+            LIMIT $1
+        ",
+    )
+    .bind(AUDIT_LOG_LEN)
+    .fetch_all(&pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// The moderation console, for removing scores and banning names.
+#[component]
+pub fn Admin() -> impl IntoView {
+    let i18n = use_i18n();
+    let login = create_server_action::<AdminLogin>();
+    let logout = create_server_action::<AdminLogout>();
+
+    let admin = create_resource(
+        move || (login.version()(), logout.version()()),
+        |_| get_admin(),
+    );
+
+    let error = move || {
+        login.value()()
+            .and_then(Result::err)
+            .map(|error| i18n.server_error(error))
+    };
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || match admin() {
+                Some(Ok(Some(name))) => view! {
+                    <AdminConsole name />
+
+                    <ActionForm action=logout>
+                        <div class="btns">
+                            <div class="btn">
+                                <input type="submit" value=move || i18n.t("account.log_out") />
+                            </div>
+                        </div>
+                    </ActionForm>
+                }
+                .into_view(),
+
+                _ => view! {
+                    <div class="panel account-panel">
+                        <div class="panel-label">{move || i18n.t("admin.title")}</div>
+                        <ActionForm action=login>
+                            <table class="panel-table">
+                                <tr class="panel-row">
+                                    <td class="panel-row-label">
+                                        <label for="username">{move || i18n.t("account.name")}</label>
+                                    </td>
+                                    <td>
+                                        <input type="text" name="username" size="12" />
+                                    </td>
+                                </tr>
+                                <tr class="panel-row">
+                                    <td class="panel-row-label">
+                                        <label for="password">{move || i18n.t("account.password")}</label>
+                                    </td>
+                                    <td>
+                                        <input type="password" name="password" size="12" />
+                                    </td>
+                                </tr>
+                            </table>
+                            <div class="btns">
+                                <div class="btn">
+                                    <input type="submit" value=move || i18n.t("account.log_in") />
+                                </div>
+                            </div>
+                        </ActionForm>
+                    </div>
+
+                    <div class="account-error">
+                        {error}
+                    </div>
+                }
+                .into_view(),
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn AdminConsole(name: String) -> impl IntoView {
+    let i18n = use_i18n();
+    let (search, set_search) = create_signal(String::new());
+    let (page, set_page) = create_signal(1);
+
+    let delete_score = create_server_action::<DeleteScore>();
+    let delete_scores_by = create_server_action::<DeleteScoresBy>();
+    let ban_name = create_server_action::<BanName>();
+    let unban_name = create_server_action::<UnbanName>();
+
+    let changes = move || {
+        (
+            delete_score.version()(),
+            delete_scores_by.version()(),
+            ban_name.version()(),
+            unban_name.version()(),
+        )
+    };
+
+    let entries = create_resource(
+        move || (search(), page(), changes()),
+        |(search, page, _)| async move { admin_scores(search, page).await.unwrap_or_default() },
+    );
+    let bans = create_resource(changes, |_| async { get_bans().await.unwrap_or_default() });
+    let audit_log = create_resource(changes, |_| async {
+        get_audit_log().await.unwrap_or_default()
+    });
+
+    let page_count = move || {
+        entries
+            .with(|entries| entries.as_ref().map(ScoreEntryPage::page_count))
+            .unwrap_or(1)
+    };
+
+    let error = move || {
+        [
+            delete_score.value()(),
+            delete_scores_by.value()().map(|result| result.map(|_| ())),
+            ban_name.value()(),
+            unban_name.value()(),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(Result::err)
+        .map(|error| i18n.server_error(error))
+    };
+
+    view! {
+        <div class="panel">
+            <div class="panel-label">
+                {move || i18n.t_with("account.logged_in", &[("name", &name)])}
+            </div>
+            <table class="panel-table">
+                <tr class="panel-row">
+                    <td>
+                        <input
+                            type="text"
+                            class="search"
+                            placeholder=move || i18n.t("scores.search")
+                            prop:value=search
+                            on:change=move |ev| {
+                                set_page(1);
+                                set_search(event_target_value(&ev));
+                            }
+                        />
+                    </td>
+                </tr>
+                <tr class="panel-row">
+                    <td>
+                        <ActionForm action=delete_scores_by>
+                            <input
+                                type="text"
+                                name="username"
+                                size="12"
+                                placeholder=move || i18n.t("common.name")
+                            />
+                            <input
+                                type="submit"
+                                class="admin-action"
+                                value=move || i18n.t("admin.delete_all_by")
+                            />
+                        </ActionForm>
+                    </td>
+                </tr>
+            </table>
+        </div>
+
+        <div class="account-error">
+            {error}
+        </div>
+
+        <table class="scoreboard admin">
+            <tr class="header">
+                <th>"#"</th>
+                <th>{move || i18n.t("common.name")}</th>
+                <th>{move || i18n.t("common.time")}</th>
+                <th>{move || i18n.t("stats.difficulty")}</th>
+                <th>{move || i18n.t("stats.size")}</th>
+                <th>{move || i18n.t("admin.posted")}</th>
+                <th></th>
+            </tr>
+            <Transition fallback=|| ()>
+                {move || {
+                    entries()
+                        .map(|entries| entries.entries)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .zip(1..)
+                        .map(|(entry, n)| {
+                            let ScoreEntry { id, username, .. } = entry.clone();
+                            let difficulty = format!("difficulty.{}", entry.difficulty);
+                            let size = format!("size.{}", entry.size);
+                            view! {
+                                <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                                    <td>{entry.id}</td>
+                                    <td>{entry.username}</td>
+                                    <td>{to_time(entry.time_in_seconds)}</td>
+                                    <td>{move || i18n.t(&difficulty)}</td>
+                                    <td>{move || i18n.t(&size)}</td>
+                                    <td>{entry.created_at}</td>
+                                    <td>
+                                        <input
+                                            type="button"
+                                            class="admin-action"
+                                            value=move || i18n.t("admin.delete")
+                                            on:click=move |_| delete_score.dispatch(DeleteScore { id })
+                                        />
+                                        <input
+                                            type="button"
+                                            class="admin-action"
+                                            value=move || i18n.t("admin.ban")
+                                            on:click=move |_| {
+                                                ban_name.dispatch(BanName { username: username.clone() })
+                                            }
+                                        />
+                                    </td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </Transition>
+        </table>
+
+        <div class="btns pages">
+            <div class=move || format!("btn {}", if page() > 1 { "" } else { "disabled" })>
+                <input
+                    type="button"
+                    value=move || i18n.t("scores.prev")
+                    on:click=move |_| {
+                        if page() > 1 {
+                            set_page(page() - 1);
+                        }
+                    }
+                />
+            </div>
+            <span class="page-number">
+                {move || i18n.t_with("scores.page", &[("page", &page()), ("pages", &page_count())])}
+            </span>
+            <div class=move || format!("btn {}", if page() < page_count() { "" } else { "disabled" })>
+                <input
+                    type="button"
+                    value=move || i18n.t("scores.next")
+                    on:click=move |_| {
+                        if page() < page_count() {
+                            set_page(page() + 1);
+                        }
+                    }
+                />
+            </div>
+        </div>
+
+        <table class="scoreboard admin">
+            <tr class="header">
+                <th>{move || i18n.t("admin.banned_name")}</th>
+                <th>{move || i18n.t("admin.banned_by")}</th>
+                <th>{move || i18n.t("admin.banned_at")}</th>
+                <th></th>
+            </tr>
+            <Transition fallback=|| ()>
+                {move || {
+                    bans()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .zip(1..)
+                        .map(|(ban, n)| {
+                            let username = ban.username.clone();
+                            view! {
+                                <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                                    <td>{ban.username}</td>
+                                    <td>{ban.banned_by}</td>
+                                    <td>{ban.banned_at}</td>
+                                    <td>
+                                        <input
+                                            type="button"
+                                            class="admin-action"
+                                            value=move || i18n.t("admin.unban")
+                                            on:click=move |_| {
+                                                unban_name.dispatch(UnbanName { username: username.clone() })
+                                            }
+                                        />
+                                    </td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </Transition>
+        </table>
+
+        <table class="scoreboard admin">
+            <tr class="header">
+                <th>{move || i18n.t("admin.when")}</th>
+                <th>{move || i18n.t("admin.admin")}</th>
+                <th>{move || i18n.t("admin.action")}</th>
+                <th>{move || i18n.t("admin.details")}</th>
+            </tr>
+            <Transition fallback=|| ()>
+                {move || {
+                    audit_log()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .zip(1..)
+                        .map(|(entry, n)| {
+                            view! {
+                                <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                                    <td>{entry.created_at}</td>
+                                    <td>{entry.admin}</td>
+                                    <td>{entry.action}</td>
+                                    <td>{entry.details}</td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </Transition>
+        </table>
+    }
+}
@@ -3,6 +3,7 @@ use leptos::*;
 use leptos_router::A;
 
 use crate::app_error::AppError;
+use crate::i18n::use_i18n;
 
 #[cfg(feature = "ssr")]
 use leptos_axum::ResponseOptions;
@@ -29,7 +30,13 @@ pub fn Error(
         .into_iter()
         .filter_map(|(_k, v)| v.downcast_ref::<AppError>().cloned())
         .collect();
-    println!("Errors: {errors:#?}");
+    log::warn!("rendering errors: {errors:?}");
+    let i18n = use_i18n();
+    let title = if errors.len() > 1 {
+        "error.title_many"
+    } else {
+        "error.title"
+    };
 
     // Only the response code for the first error is actually sent from the server
     // this may be customized by the specific application
@@ -41,7 +48,7 @@ pub fn Error(
     }}
 
     view! {
-        <h1>{if errors.len() > 1 {"Errors"} else {"Error"}}</h1>
+        <h1>{move || i18n.t(title)}</h1>
         <For
             // a function that returns the items we're iterating over; a signal is fine
             each= move || {errors.clone().into_iter().enumerate()}
@@ -49,17 +56,16 @@ pub fn Error(
             key=|(index, _error)| *index
             // renders each item to a view
             children= move |error| {
-                let error_string = error.1.to_string();
                 let error_code = error.1.status_code();
                 view! {
                     <h2>{error_code.to_string()}</h2>
-                    <p>{error_string}</p>
+                    <p>{move || i18n.error(&error.1)}</p>
                 }
             }
         />
         <div class="buttons">
             <div class="button-item">
-                <A href="/">"Return"</A>
+                <A href="/">{move || i18n.t("common.return")}</A>
             </div>
         </div>
     }
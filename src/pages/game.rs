@@ -4,6 +4,7 @@ use leptos_router::*;
 use crate::app_error::AppError;
 use crate::game_logic::{CellInteraction, CellKind, GameParams, GameState};
 use crate::game_settings::Size;
+use crate::i18n::use_i18n;
 use crate::pages::Error;
 
 const NUM_SVGS: [&str; 9] = [
@@ -25,6 +26,7 @@ const FLAG_SVG: &str = include_str!("../../svgs/flag.svg");
 #[component]
 pub fn Game() -> impl IntoView {
     window_event_listener(ev::contextmenu, |ev| ev.prevent_default());
+    let i18n = use_i18n();
 
     use_query::<GameParams>().with_untracked(|params| match params {
         Ok(params) => {
@@ -36,6 +38,10 @@ pub fn Game() -> impl IntoView {
             provide_context(game_state_read);
             provide_context(game_state_write);
 
+            on_cleanup(move || {
+                game_state_read.try_with_untracked(|game_state| game_state.abandon());
+            });
+
             view! {
                 <div class="btns">
                     <div class=move || { format!("btn {}", if new_game_enabled() { "" } else { "disabled" }) }>
@@ -52,12 +58,12 @@ pub fn Game() -> impl IntoView {
 
                             class=move || { if new_game_enabled() { "" } else { "disabled" } }
                         >
-                            "New Game"
+                            {move || i18n.t("common.new_game")}
                         </A>
                     </div>
                     <div class="btn">
                         <A href="/">
-                            "Return"
+                            {move || i18n.t("common.return")}
                         </A>
                     </div>
                 </div>
@@ -84,14 +90,62 @@ pub fn Game() -> impl IntoView {
 /// Displays the timer and current score.
 #[component]
 fn Info() -> impl IntoView {
-    let info = use_context::<ReadSignal<GameState>>()
+    let (info, post_score) = use_context::<ReadSignal<GameState>>()
         .expect("game state exists")
-        .with_untracked(|game_state| game_state.info_signal());
+        .with_untracked(|game_state| (game_state.info_signal(), game_state.post_score_action()));
+    let i18n = use_i18n();
+
+    // the action forgets its input once it finishes, so keep the last score for retrying
+    let (last_score, set_last_score) = create_signal(None);
+    create_effect(move |_| {
+        if let Some(score) = post_score.input()() {
+            set_last_score(Some(score));
+        }
+    });
+
+    let score_error = move || {
+        post_score.value()()
+            .and_then(Result::err)
+            .map(|error| AppError::from_server_error(&error))
+    };
 
     view! {
         <h2 class="info">
             { move || info.with(|info| info.to_view()) }
         </h2>
+        { move || score_error().map(|error| {
+            let retryable = error.is_retryable();
+            let message = i18n.t_with(
+                if retryable { "game.score_not_saved_yet" } else { "game.score_not_saved" },
+                &[("error", &i18n.error(&error))],
+            );
+
+            view! {
+                <div class="toast" role="alert">
+                    <span>{message}</span>
+                    <Show when=move || retryable && !post_score.pending()() fallback=|| ()>
+                        <div class="btn">
+                            <input
+                                type="button"
+                                value=move || i18n.t("common.retry")
+                                on:click=move |_| {
+                                    if let Some(score) = last_score.get_untracked() {
+                                        post_score.dispatch(score);
+                                    }
+                                }
+                            />
+                        </div>
+                    </Show>
+                    <div class="btn">
+                        <input
+                            type="button"
+                            value=move || i18n.t("common.dismiss")
+                            on:click=move |_| post_score.value().set(None)
+                        />
+                    </div>
+                </div>
+            }
+        }) }
     }
 }
 
@@ -1,4 +1,4 @@
-use std::{ops::RangeInclusive, rc::Rc};
+use std::rc::Rc;
 
 use gloo_timers::future::TimeoutFuture;
 use leptos::*;
@@ -7,18 +7,18 @@ use wasm_bindgen::JsCast;
 use web_sys::HtmlFormElement;
 
 use crate::{
-    game_settings::{apply_setting, fetch_setting, Difficulty, Size, Username},
-    utils::to_title,
+    game_history::{self, PlayedGame},
+    game_settings::{
+        apply_setting, fetch_setting, valid_chars, CustomTheme, Difficulty, Outcome, Palette, Size,
+        Theme, Username, THEME_COLORS, USERNAME_BOUNDS,
+    },
+    i18n::{use_i18n, Locale},
+    utils::to_time,
 };
 
-const USERNAME_BOUNDS: RangeInclusive<usize> = 3..=10;
 const DICE_SVG: &str = include_str!("../../svgs/dice.svg");
-
-fn valid_chars(username: &str) -> bool {
-    username
-        .chars()
-        .all(|c| c.is_ascii_alphabetic() || c == '_')
-}
+/// Games listed under Recent Games.
+const RECENT_GAMES: usize = 5;
 
 /// Renders the home page.
 #[component]
@@ -31,6 +31,15 @@ pub fn HomePage() -> impl IntoView {
         create_signal(fetch_setting::<Difficulty>("difficulty").unwrap_or_default());
     let (size, set_size) = create_signal(fetch_setting::<Size>("size").unwrap_or_default());
     let (form_action, set_form_action) = create_signal("/");
+    let i18n = use_i18n();
+    let (theme, set_theme) = (
+        expect_context::<ReadSignal<Theme>>(),
+        expect_context::<WriteSignal<Theme>>(),
+    );
+    let (palette, set_palette) = (
+        expect_context::<ReadSignal<Palette>>(),
+        expect_context::<WriteSignal<Palette>>(),
+    );
 
     let username_ref = create_node_ref::<html::Input>();
     let error_ref = create_node_ref::<html::Span>();
@@ -79,7 +88,7 @@ pub fn HomePage() -> impl IntoView {
 
     let on_settings_submit = move |ev: ev::SubmitEvent| {
         let Username { name, stable } = username();
-        if USERNAME_BOUNDS.contains(&name.len()) && valid_chars(&name) {
+        if Username::validate(&name).is_ok() {
             if stable {
                 apply_setting("username", &name);
             }
@@ -127,11 +136,11 @@ pub fn HomePage() -> impl IntoView {
             })
         >
             <div class="panel">
-                <div class="panel-label">"Settings"</div>
+                <div class="panel-label">{move || i18n.t("home.settings")}</div>
                 <table class="panel-table">
                     <tr class="panel-row">
                         <td class="panel-row-label">
-                            <label for="username">"Name:"</label>
+                            <label for="username">{move || i18n.t("home.name")}</label>
                         </td>
                         <td>
                             <input
@@ -149,7 +158,7 @@ pub fn HomePage() -> impl IntoView {
                             />
                             <div class="username-error-container">
                                 <span class="username-error" node_ref=error_ref>
-                                    "Name must be 3-10 alphanumeric characters and underscores"
+                                    {move || i18n.t("home.name_rules")}
                                 </span>
                             </div>
                         </td>
@@ -157,7 +166,7 @@ pub fn HomePage() -> impl IntoView {
 
                     <tr class="panel-row">
                         <td class="panel-row-label">
-                            <label for="difficulty">"Difficulty:"</label>
+                            <label for="difficulty">{move || i18n.t("home.difficulty")}</label>
                         </td>
                         <td>
                             <select name="difficulty" node_ref=difficulty_ref>
@@ -172,7 +181,7 @@ pub fn HomePage() -> impl IntoView {
                                             value=curr_difficulty.to_string()
                                             selected=move || difficulty() == *curr_difficulty
                                         >
-                                        {to_title(&curr_difficulty)}
+                                        {move || i18n.t(&format!("difficulty.{curr_difficulty}"))}
                                         </option>
                                     }
                                 }).collect_view()
@@ -183,7 +192,7 @@ pub fn HomePage() -> impl IntoView {
 
                     <tr class="panel-row">
                         <td class="panel-row-label">
-                            <label for="size">"Board Size:"</label>
+                            <label for="size">{move || i18n.t("home.size")}</label>
                         </td>
                         <td>
                             <select name="size" node_ref=size_ref>
@@ -198,7 +207,37 @@ pub fn HomePage() -> impl IntoView {
                                             value=curr_size.to_string()
                                             selected=move || size() == *curr_size
                                         >
-                                        {to_title(&curr_size)}
+                                        {move || i18n.t(&format!("size.{curr_size}"))}
+                                        </option>
+                                    }
+                                }).collect_view()
+                            }
+                            </select>
+                        </td>
+                    </tr>
+
+                    <tr class="panel-row">
+                        <td class="panel-row-label">
+                            <label for="locale">{move || i18n.t("home.language")}</label>
+                        </td>
+                        <td>
+                            // unnamed so it isn't sent with the form, as it applies straight away
+                            <select
+                                id="locale"
+                                on:change=move |ev| {
+                                    if let Ok(locale) = event_target_value(&ev).parse() {
+                                        i18n.set_locale(locale);
+                                    }
+                                }
+                            >
+                            {
+                                Locale::ALL.iter().map(|curr_locale| {
+                                    view! {
+                                        <option
+                                            value=curr_locale.to_string()
+                                            selected=move || i18n.locale() == *curr_locale
+                                        >
+                                        {curr_locale.name()}
                                         </option>
                                     }
                                 }).collect_view()
@@ -206,6 +245,74 @@ pub fn HomePage() -> impl IntoView {
                             </select>
                         </td>
                     </tr>
+
+                    <tr class="panel-row">
+                        <td class="panel-row-label">
+                            <label for="theme">{move || i18n.t("home.theme")}</label>
+                        </td>
+                        <td>
+                            <select
+                                id="theme"
+                                on:change=move |ev| {
+                                    if let Ok(new_theme) = event_target_value(&ev).parse() {
+                                        apply_setting("theme", &new_theme);
+                                        set_theme(new_theme);
+                                    }
+                                }
+                            >
+                            {
+                                Theme::ALL.iter().map(|curr_theme| {
+                                    view! {
+                                        <option
+                                            value=curr_theme.to_string()
+                                            selected=move || theme() == *curr_theme
+                                        >
+                                        {move || i18n.t(&format!("mode.{curr_theme}"))}
+                                        </option>
+                                    }
+                                }).collect_view()
+                            }
+                            </select>
+                        </td>
+                    </tr>
+
+                    <tr class="panel-row">
+                        <td class="panel-row-label">
+                            <label for="palette">{move || i18n.t("home.palette")}</label>
+                        </td>
+                        <td>
+                            <select
+                                id="palette"
+                                on:change=move |ev| {
+                                    if let Ok(new_palette) = event_target_value(&ev).parse() {
+                                        apply_setting("palette", &new_palette);
+                                        set_palette(new_palette);
+                                    }
+                                }
+                            >
+                            {
+                                Palette::ALL.iter().map(|curr_palette| {
+                                    view! {
+                                        <option
+                                            value=curr_palette.to_string()
+                                            selected=move || palette() == *curr_palette
+                                        >
+                                        {move || i18n.t(&format!("palette.{curr_palette}"))}
+                                        </option>
+                                    }
+                                }).collect_view()
+                            }
+                            </select>
+                        </td>
+                    </tr>
+
+                    <Show when=move || palette() == Palette::Custom fallback=|| ()>
+                        <tr class="panel-row">
+                            <td colspan="2">
+                                <CustomColors />
+                            </td>
+                        </tr>
+                    </Show>
                 </table>
             </div>
 
@@ -213,18 +320,131 @@ pub fn HomePage() -> impl IntoView {
                 <div class="btn">
                     <input
                         type="submit"
-                        value="New Game"
+                        value=move || i18n.t("common.new_game")
                         on:click=move |_| set_form_action("/game")
                     />
                 </div>
                 <div class="btn">
                     <input
                         type="submit"
-                        value="Scores"
+                        value=move || i18n.t("home.scores")
                         on:click=move |_| set_form_action("/scores")
                     />
                 </div>
+                <div class="btn">
+                    <A href="/account">
+                        {move || i18n.t("home.account")}
+                    </A>
+                </div>
             </div>
         </Form>
+
+        <RecentGames />
+    }
+}
+
+/// Color pickers for each of the custom palette's colors.
+#[component]
+fn CustomColors() -> impl IntoView {
+    let i18n = use_i18n();
+    let (custom_theme, set_custom_theme) = (
+        expect_context::<ReadSignal<CustomTheme>>(),
+        expect_context::<WriteSignal<CustomTheme>>(),
+    );
+    let save = move || apply_setting("custom_theme", &custom_theme.get_untracked());
+
+    view! {
+        <div class="custom-colors">
+            {
+                THEME_COLORS.iter().enumerate().map(|(index, (variable, _))| {
+                    let id = format!("color-{variable}");
+                    let label = move || match variable.strip_prefix("num-") {
+                        Some(number) => i18n.t_with("theme.number", &[("number", &number)]),
+                        None => i18n.t(&format!("theme.{variable}")),
+                    };
+
+                    view! {
+                        <label for=id.clone()>
+                            <input
+                                type="color"
+                                id=id
+                                prop:value=move || custom_theme.with(|theme| theme.color(index).to_owned())
+                                on:input=move |ev| {
+                                    set_custom_theme.update(|theme| theme.set_color(index, &event_target_value(&ev)));
+                                    save();
+                                }
+                            />
+                            {label}
+                        </label>
+                    }
+                }).collect_view()
+            }
+        </div>
+        <div class="btn">
+            <input
+                type="button"
+                value=move || i18n.t("theme.reset")
+                on:click=move |_| {
+                    set_custom_theme(CustomTheme::default());
+                    save();
+                }
+            />
+        </div>
+    }
+}
+
+/// The last games finished in this browser, which are shown even if the server is down.
+#[component]
+fn RecentGames() -> impl IntoView {
+    let i18n = use_i18n();
+    let (games, set_games) = create_signal(Vec::<PlayedGame>::new());
+    // local storage can only be read once the page is running in the browser
+    Effect::new(move |_| {
+        let mut played_games = game_history::played_games();
+        played_games.truncate(RECENT_GAMES);
+        set_games(played_games);
+    });
+
+    view! {
+        <Show when=move || games.with(|games| !games.is_empty()) fallback=|| ()>
+            <div class="panel">
+                <div class="panel-label">{move || i18n.t("home.recent_games")}</div>
+                <table class="scoreboard recent-games">
+                    <tr class="header">
+                        <th>{move || i18n.t("home.finished")}</th>
+                        <th>{move || i18n.t("home.board")}</th>
+                        <th>{move || i18n.t("home.result")}</th>
+                        <th>{move || i18n.t("common.time")}</th>
+                    </tr>
+                    {move || games()
+                        .into_iter()
+                        .zip(1..)
+                        .map(|(game, n)| {
+                            let result = match (game.outcome, game.personal_best) {
+                                (Outcome::Won, true) => format!("{} 🏆", i18n.t("outcome.won")),
+                                (Outcome::Won, false) => i18n.t("outcome.won"),
+                                _ => i18n.t("outcome.lost"),
+                            };
+                            let board = i18n.t_with(
+                                "home.board_name",
+                                &[
+                                    ("difficulty", &i18n.t(&format!("difficulty.{}", game.difficulty))),
+                                    ("size", &i18n.t(&format!("size.{}", game.size))),
+                                ],
+                            );
+
+                            view! {
+                                <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                                    <td>{game.finished_at_local()}</td>
+                                    <td>{board}</td>
+                                    <td>{result}</td>
+                                    <td>{to_time(game.time_in_seconds)}</td>
+                                </tr>
+                            }
+                        })
+                        .collect_view()}
+                </table>
+            </div>
+        </Show>
     }
 }
@@ -1,71 +1,268 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use leptos::*;
 use leptos_router::*;
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_error::AppError,
-    game_settings::{Difficulty, Size},
+    game_settings::{Difficulty, Size, Username},
+    i18n::use_i18n,
     pages::Error,
-    utils::{to_time, to_title},
+    utils::to_time,
 };
 
-const MAX_SCORES: usize = 10;
+/// Scores on each page of a scoreboard, unless the server is configured otherwise.
+pub const MAX_SCORES: usize = 10;
+/// The longest time a score can have, which is as far as the timer counts before its
+/// minutes wrap around.
+pub const MAX_TIME_IN_SECONDS: i64 = 99 * 60 - 1;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 pub struct Score {
-    username: String,
-    time_in_seconds: i64,
+    pub rank: i64,
+    pub username: String,
+    pub time_in_seconds: i64,
+}
+
+/// How far back the scoreboard looks for scores.
+#[derive(PartialEq, Copy, Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Today,
+    Week,
+    Month,
+    #[default]
+    All,
+}
+
+impl Period {
+    /// When the period began, formatted like sqlite's `CURRENT_TIMESTAMP`.
+    /// Days, weeks (starting on Monday) and months are in UTC.
+    #[cfg(feature = "ssr")]
+    pub(crate) fn start(self) -> Option<String> {
+        use chrono::{Datelike, Duration, Utc};
+
+        let today = Utc::now().date_naive();
+        let start = match self {
+            Self::Today => today,
+            Self::Week => today - Duration::days(today.weekday().num_days_from_monday().into()),
+            Self::Month => today.with_day(1).expect("every month has a first day"),
+            Self::All => return None,
+        };
+
+        Some(start.format("%Y-%m-%d 00:00:00").to_string())
+    }
+}
+
+impl FromStr for Period {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
+/// One page of the scoreboard, along with how many scores match its filters in total.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScorePage {
+    pub scores: Vec<Score>,
+    pub total: i64,
+    pub per_page: i64,
+}
+
+impl ScorePage {
+    /// Pages that haven't loaded yet are taken to be the default size.
+    fn per_page(&self) -> i64 {
+        if self.per_page > 0 {
+            self.per_page
+        } else {
+            MAX_SCORES as i64
+        }
+    }
+
+    fn page_count(&self) -> i64 {
+        ((self.total + self.per_page() - 1) / self.per_page()).max(1)
+    }
+}
+
+/// Whether the scoreboard lists every run or only each player's personal best.
+#[derive(PartialEq, Copy, Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreMode {
+    #[default]
+    Best,
+    All,
+}
+
+impl FromStr for ScoreMode {
+    type Err = serde::de::value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
+impl Display for ScoreMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
 }
 
 #[server(GetScores)]
-async fn get_scores(difficulty: Difficulty, size: Size) -> Result<Vec<Score>, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let (difficulty, size) = (difficulty.to_string(), size.to_string());
-
-    sqlx::query_as!(
-        Score,
-        "
-            SELECT username, time_in_seconds
-            FROM scores
-            WHERE difficulty=?
-                AND size=?
-            ORDER BY time_in_seconds
-            LIMIT ?
-        ",
+#[cfg_attr(
+    feature = "ssr",
+    tracing::instrument(parent = crate::telemetry::request_span_id(), err(level = "warn"))
+)]
+async fn get_scores(
+    difficulty: Difficulty,
+    size: Size,
+    period: Period,
+    mode: ScoreMode,
+    search: String,
+    page: i64,
+) -> Result<ScorePage, ServerFnError> {
+    use crate::{
+        config::Config,
+        score_store::{ScoreQuery, SharedScoreStore, MAX_PAGE},
+    };
+
+    if page > MAX_PAGE {
+        return Err(AppError::server_error(AppError::PageOutOfRange));
+    }
+
+    let store = expect_context::<SharedScoreStore>();
+    let config = expect_context::<Config>();
+    let query = ScoreQuery {
         difficulty,
         size,
-        MAX_SCORES as i64
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(Into::into)
+        period,
+        mode,
+        search,
+        username: None,
+        page,
+        per_page: config.leaderboard_size,
+    };
+
+    store
+        .fetch_scores(&query)
+        .await
+        .map_err(AppError::server_error)
 }
 
+/// The best rank a player holds on a board, if they have any scores on it.
+#[server(GetRank)]
+async fn get_rank(
+    difficulty: Difficulty,
+    size: Size,
+    period: Period,
+    mode: ScoreMode,
+    username: String,
+) -> Result<Option<i64>, ServerFnError> {
+    use crate::score_store::SharedScoreStore;
+
+    expect_context::<SharedScoreStore>()
+        .best_rank(difficulty, size, period, mode, username)
+        .await
+        .map_err(AppError::server_error)
+}
+
+/// Records a won game and adds its time to the scoreboard. Clients pick a `game_id` for
+/// each game, so sending the same score again does nothing.
 #[server(PostScore)]
+#[cfg_attr(
+    feature = "ssr",
+    tracing::instrument(parent = crate::telemetry::request_span_id(), err(level = "warn"))
+)]
 pub async fn post_score(
     username: String,
     time_in_seconds: i64,
     difficulty: Difficulty,
     size: Size,
+    game_id: String,
 ) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let (difficulty, size) = (difficulty.to_string(), size.to_string());
-
-    sqlx::query_as!(
-        Score,
-        "
-            INSERT INTO scores(username, time_in_seconds, difficulty, size)
-            VALUES (?, ?, ?, ?)
-        ",
-        username,
+    use crate::game_settings::Outcome;
+    use crate::metrics::Metrics;
+    use crate::pages::{account::resolve_username, stats::insert_game};
+    use crate::rate_limit::RateLimits;
+    use crate::score_store::SharedScoreStore;
+
+    /// Longest game id accepted from clients.
+    const MAX_GAME_ID_LEN: usize = 64;
+
+    if game_id.is_empty() || game_id.len() > MAX_GAME_ID_LEN {
+        return Err(ServerFnError::Args("Invalid game id".into()));
+    }
+    check_time(time_in_seconds).map_err(AppError::server_error)?;
+
+    let pool = expect_context::<sqlx::AnyPool>();
+    let username = resolve_username(&pool, username)
+        .await
+        .map_err(AppError::server_error)?;
+    if expect_context::<RateLimits>()
+        .check_score_by(&username)
+        .is_err()
+    {
+        return Err(AppError::server_error(AppError::RateLimited));
+    }
+
+    // both inserts skip ids they've seen, so a retry after either fails records what's missing
+    let new_game = insert_game(
+        &pool,
+        &username,
+        Outcome::Won,
         time_in_seconds,
         difficulty,
         size,
+        Some(&game_id),
     )
-    .execute(&pool)
     .await
-    .map(|_| ())
-    .map_err(Into::into)
+    .map_err(AppError::server_error)?;
+    let new_score = expect_context::<SharedScoreStore>()
+        .insert_score(&username, time_in_seconds, difficulty, size, Some(&game_id))
+        .await
+        .map_err(AppError::server_error)?;
+
+    let metrics = expect_context::<Metrics>();
+    if new_game {
+        metrics.game_finished(Outcome::Won, difficulty, size);
+    }
+    if new_score {
+        metrics.score_posted(difficulty, size);
+    }
+
+    Ok(())
+}
+
+/// Checks that a won game took a time that can be a score.
+#[cfg(feature = "ssr")]
+fn check_time(time_in_seconds: i64) -> Result<(), AppError> {
+    if (1..=MAX_TIME_IN_SECONDS).contains(&time_in_seconds) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidTime)
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ScoreboardPosition {
+    search: Memo<Option<String>>,
+    page: Memo<Option<i64>>,
+}
+
+#[derive(Copy, Clone)]
+struct SetScoreboardPosition {
+    set_search: SignalSetter<Option<String>>,
+    set_page: SignalSetter<Option<i64>>,
 }
 
 /// Displays the scoreboard.
@@ -73,19 +270,39 @@ pub async fn post_score(
 pub fn Scores() -> impl IntoView {
     let (difficulty, set_difficulty) = create_query_signal::<Difficulty>("difficulty");
     let (size, set_size) = create_query_signal::<Size>("size");
+    let (period, set_period) = create_query_signal::<Period>("period");
+    let (search, set_search) = create_query_signal::<String>("search");
+    let (page, set_page) = create_query_signal::<i64>("page");
     provide_context((difficulty, size));
     provide_context((set_difficulty, set_size));
+    let (mode, set_mode) = create_query_signal::<ScoreMode>("mode");
+    let i18n = use_i18n();
+    provide_context(period);
+    provide_context(set_period);
+    provide_context(mode);
+    provide_context(set_mode);
+    provide_context(ScoreboardPosition { search, page });
+    provide_context(SetScoreboardPosition {
+        set_search,
+        set_page,
+    });
 
     match (difficulty.get_untracked(), size.get_untracked()) {
         (Some(difficulty), Some(size)) => view! {
-            <ScoreFilters difficulty size />
+            <ScoreFilters
+                difficulty
+                size
+                period=period.get_untracked().unwrap_or_default()
+                mode=mode.get_untracked().unwrap_or_default()
+                search=search.get_untracked().unwrap_or_default()
+            />
 
             <Scoreboard />
 
             <div class="btns">
                 <div class="btn">
                     <A href="/">
-                        "Return"
+                        {move || i18n.t("common.return")}
                     </A>
                 </div>
             </div>
@@ -105,19 +322,33 @@ pub fn Scores() -> impl IntoView {
 }
 
 #[component]
-fn ScoreFilters(difficulty: Difficulty, size: Size) -> impl IntoView {
+fn ScoreFilters(
+    difficulty: Difficulty,
+    size: Size,
+    period: Period,
+    mode: ScoreMode,
+    search: String,
+) -> impl IntoView {
     let (set_difficulty, set_size) =
         expect_context::<(SignalSetter<Option<Difficulty>>, SignalSetter<Option<Size>>)>();
+    let set_period = expect_context::<SignalSetter<Option<Period>>>();
+    let set_mode = expect_context::<SignalSetter<Option<ScoreMode>>>();
+    let SetScoreboardPosition {
+        set_search,
+        set_page,
+    } = expect_context();
+    let i18n = use_i18n();
 
     view! {
         <div class="panel">
             <div class="panel-label">
-                "Filters"
+                {move || i18n.t("scores.filters")}
             </div>
             <table class="panel-table">
                 <tr class="panel-row">
                     <td>
                         <select on:change=move |ev| {
+                            set_page(None);
                             set_difficulty(Some(event_target_value(&ev).parse().expect("value is a difficulty")));
                         }>
                         {
@@ -132,7 +363,7 @@ fn ScoreFilters(difficulty: Difficulty, size: Size) -> impl IntoView {
                                         selected=move || difficulty == *curr_difficulty
                                         on:click=move |_| set_difficulty(Some(*curr_difficulty))
                                     >
-                                    {to_title(&curr_difficulty)}
+                                    {move || i18n.t(&format!("difficulty.{curr_difficulty}"))}
                                     </option>
                                 }
                             }).collect_view()
@@ -141,6 +372,7 @@ fn ScoreFilters(difficulty: Difficulty, size: Size) -> impl IntoView {
                     </td>
                     <td>
                         <select on:change=move |ev| {
+                            set_page(None);
                             set_size(Some(event_target_value(&ev).parse().expect("value is a size")));
                         }>
                         {
@@ -154,7 +386,68 @@ fn ScoreFilters(difficulty: Difficulty, size: Size) -> impl IntoView {
                                         value=curr_size.to_string()
                                         selected=move || size == *curr_size
                                     >
-                                    {to_title(&curr_size)}
+                                    {move || i18n.t(&format!("size.{curr_size}"))}
+                                    </option>
+                                }
+                            }).collect_view()
+                        }
+                        </select>
+                    </td>
+                    <td>
+                        <select on:change=move |ev| {
+                            set_page(None);
+                            set_period(Some(event_target_value(&ev).parse().expect("value is a period")));
+                        }>
+                        {
+                            [
+                                (Period::Today, "scores.today"),
+                                (Period::Week, "scores.week"),
+                                (Period::Month, "scores.month"),
+                                (Period::All, "scores.all_time"),
+                            ].iter().map(|(curr_period, label)| {
+                                view! {
+                                    <option
+                                        value=curr_period.to_string()
+                                        selected=move || period == *curr_period
+                                    >
+                                    {move || i18n.t(label)}
+                                    </option>
+                                }
+                            }).collect_view()
+                        }
+                        </select>
+                    </td>
+                </tr>
+                <tr class="panel-row">
+                    <td colspan="2">
+                        <input
+                            type="text"
+                            class="search"
+                            placeholder=move || i18n.t("scores.search")
+                            prop:value=search
+                            on:change=move |ev| {
+                                let search = event_target_value(&ev);
+                                set_page(None);
+                                set_search((!search.is_empty()).then_some(search));
+                            }
+                        />
+                    </td>
+                    <td>
+                        <select on:change=move |ev| {
+                            set_page(None);
+                            set_mode(Some(event_target_value(&ev).parse().expect("value is a score mode")));
+                        }>
+                        {
+                            [
+                                (ScoreMode::Best, "scores.best"),
+                                (ScoreMode::All, "scores.all_runs"),
+                            ].iter().map(|(curr_mode, label)| {
+                                view! {
+                                    <option
+                                        value=curr_mode.to_string()
+                                        selected=move || mode == *curr_mode
+                                    >
+                                    {move || i18n.t(label)}
                                     </option>
                                 }
                             }).collect_view()
@@ -170,9 +463,58 @@ fn ScoreFilters(difficulty: Difficulty, size: Size) -> impl IntoView {
 #[component]
 fn Scoreboard() -> impl IntoView {
     let (difficulty, size) = expect_context::<(Memo<Option<Difficulty>>, Memo<Option<Size>>)>();
-    let filters = move || (difficulty().unwrap_or_default(), size().unwrap_or_default());
-    let score_getter = create_resource(filters, |(difficulty, size)| async move {
-        get_scores(difficulty, size).await.unwrap_or_default()
+    let period = expect_context::<Memo<Option<Period>>>();
+    let mode = expect_context::<Memo<Option<ScoreMode>>>();
+    let ScoreboardPosition { search, page } = expect_context();
+    let SetScoreboardPosition {
+        set_search,
+        set_page,
+    } = expect_context();
+    let username = expect_context::<ReadSignal<Username>>();
+    let i18n = use_i18n();
+
+    let current_page = move || page().unwrap_or(1).max(1);
+    let board = move || {
+        (
+            difficulty().unwrap_or_default(),
+            size().unwrap_or_default(),
+            period().unwrap_or_default(),
+            mode().unwrap_or_default(),
+        )
+    };
+    let score_getter = create_resource(
+        move || (board(), search().unwrap_or_default(), current_page()),
+        |((difficulty, size, period, mode), search, page)| {
+            get_scores(difficulty, size, period, mode, search, page)
+        },
+    );
+    let page_count = move || {
+        score_getter
+            .with(|score_page| loaded(score_page).map(ScorePage::page_count))
+            .unwrap_or(1)
+    };
+
+    let export_url = move |format: &str| {
+        let (difficulty, size, period, mode) = board();
+        let search = escape(&search().unwrap_or_default());
+        format!(
+            "/api/v1/export?format={format}&difficulty={difficulty}&size={size}&period={period}&mode={mode}&search={search}"
+        )
+    };
+
+    let jump_to_rank = create_action(move |&()| {
+        let (difficulty, size, period, mode) = board();
+        let name = username.get_untracked().name;
+        let per_page =
+            untrack(|| score_getter.with(|score_page| loaded(score_page).map(ScorePage::per_page)))
+                .unwrap_or(MAX_SCORES as i64);
+
+        async move {
+            if let Ok(Some(rank)) = get_rank(difficulty, size, period, mode, name).await {
+                set_search(None);
+                set_page(Some((rank - 1) / per_page + 1));
+            }
+        }
     });
 
     view! {
@@ -183,45 +525,117 @@ fn Scoreboard() -> impl IntoView {
                         "#"
                     </th>
                     <th class="name">
-                        "Name"
+                        {move || i18n.t("common.name")}
                     </th>
                     <th class="time">
-                        "Time"
+                        {move || i18n.t("common.time")}
                     </th>
                 </tr>
-                <Transition fallback=move || view! { <ScoreRows scores=vec![] /> }>
-                    {move || view! { <ScoreRows scores=score_getter().unwrap_or_default() /> }}
+                <Transition fallback=move || view! { <ScoreRows score_page=ScorePage::default() /> }>
+                    {move || match score_getter() {
+                        Some(Err(error)) => view! {
+                            <tr class="load-error" role="alert">
+                                <td colspan="3">
+                                    <span>{ move || i18n.t_with("scores.load_error", &[("error", &i18n.error(&AppError::from_server_error(&error)))]) }</span>
+                                    <div class="btn">
+                                        <input type="button" value=move || i18n.t("common.retry") on:click=move |_| score_getter.refetch() />
+                                    </div>
+                                </td>
+                            </tr>
+                        }.into_view(),
+                        score_page => view! {
+                            <ScoreRows score_page=score_page.and_then(Result::ok).unwrap_or_default() />
+                        },
+                    }}
                 </Transition>
             </table>
         </div>
+
+        <div class="btns pages">
+            <div class=move || format!("btn {}", if current_page() > 1 { "" } else { "disabled" })>
+                <input
+                    type="button"
+                    value=move || i18n.t("scores.prev")
+                    on:click=move |_| {
+                        if current_page() > 1 {
+                            set_page(Some(current_page() - 1));
+                        }
+                    }
+                />
+            </div>
+            <span class="page-number">
+                {move || i18n.t_with("scores.page", &[("page", &current_page()), ("pages", &page_count())])}
+            </span>
+            <div class=move || format!("btn {}", if current_page() < page_count() { "" } else { "disabled" })>
+                <input
+                    type="button"
+                    value=move || i18n.t("scores.next")
+                    on:click=move |_| {
+                        if current_page() < page_count() {
+                            set_page(Some(current_page() + 1));
+                        }
+                    }
+                />
+            </div>
+            <div class="btn">
+                <input type="button" value=move || i18n.t("scores.my_rank") on:click=move |_| jump_to_rank.dispatch(()) />
+            </div>
+        </div>
+
+        <div class="downloads">
+            {move || i18n.t("scores.download")} " "
+            <a href=move || export_url("csv") download>"CSV"</a>
+            " · "
+            <a href=move || export_url("json") download>"JSON"</a>
+            " · "
+            <a href="/api/v1/export?format=csv" download>{move || i18n.t("scores.every_score")}</a>
+        </div>
     }
 }
 
+/// The page a scoreboard resource holds, if it has loaded without errors.
+fn loaded(score_page: &Option<Result<ScorePage, ServerFnError>>) -> Option<&ScorePage> {
+    score_page.as_ref()?.as_ref().ok()
+}
+
 #[component]
-fn ScoreRows(mut scores: Vec<Score>) -> impl IntoView {
-    scores.resize_with(MAX_SCORES, Default::default);
+fn ScoreRows(score_page: ScorePage) -> impl IntoView {
+    let username = expect_context::<ReadSignal<Username>>();
+    let rows = score_page.per_page() as usize;
+    let mut scores = score_page.scores;
+    scores.resize_with(rows, Default::default);
 
     scores
         .into_iter()
-        .zip(1..=MAX_SCORES)
+        .zip(1..=rows)
         .map(
             |(
                 Score {
-                    username,
+                    rank,
+                    username: name,
                     time_in_seconds,
                 },
                 n,
             )| {
+                let is_mine = {
+                    let name = name.clone();
+                    move || !name.is_empty() && username.with(|username| username.name == name)
+                };
+
                 view! {
-                    <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                    <tr class={ if n % 2 == 0 { "even" } else { "odd" }} class:mine=is_mine>
                         <td class="n">
-                            { n.to_string() }
+                            { (rank > 0).then(|| rank.to_string()) }
                         </td>
                         <td class="name">
-                            {username}
+                            {(!name.is_empty()).then(|| view! {
+                                <A href=format!("/stats/{name}")>
+                                    {name}
+                                </A>
+                            })}
                         </td>
                         <td class="time">
-                            { (time_in_seconds > 0).then(|| to_time(time_in_seconds)) }
+                            { (rank > 0).then(|| to_time(time_in_seconds)) }
                         </td>
                     </tr>
                 }
@@ -229,3 +643,21 @@ fn ScoreRows(mut scores: Vec<Score>) -> impl IntoView {
         )
         .collect_view()
 }
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_take_from_a_second_to_the_timers_limit() {
+        assert!(check_time(1).is_ok());
+        assert!(check_time(MAX_TIME_IN_SECONDS).is_ok());
+
+        for time in [i64::MIN, -1, 0, MAX_TIME_IN_SECONDS + 1, i64::MAX] {
+            assert!(
+                matches!(check_time(time), Err(AppError::InvalidTime)),
+                "{time}"
+            );
+        }
+    }
+}
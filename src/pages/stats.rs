@@ -0,0 +1,361 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game_settings::{Difficulty, Outcome, Size},
+    i18n::use_i18n,
+    utils::to_time,
+};
+
+#[cfg(feature = "ssr")]
+const MAX_TREND_POINTS: usize = 50;
+const TREND_WIDTH: f64 = 120.0;
+const TREND_HEIGHT: f64 = 24.0;
+
+/// Totals for one difficulty and board size.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CategoryStats {
+    difficulty: Difficulty,
+    size: Size,
+    won: i64,
+    lost: i64,
+    abandoned: i64,
+    best_time: Option<i64>,
+    average_time: Option<i64>,
+    /// Winning times from oldest to newest.
+    trend: Vec<i64>,
+}
+
+impl CategoryStats {
+    #[cfg(feature = "ssr")]
+    fn new(difficulty: Difficulty, size: Size) -> Self {
+        Self {
+            difficulty,
+            size,
+            won: 0,
+            lost: 0,
+            abandoned: 0,
+            best_time: None,
+            average_time: None,
+            trend: vec![],
+        }
+    }
+
+    fn played(&self) -> i64 {
+        self.won + self.lost + self.abandoned
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PlayerStats {
+    categories: Vec<CategoryStats>,
+    /// Consecutive wins, counting back from the latest game. Abandoned games don't break a streak.
+    current_streak: i64,
+    best_streak: i64,
+}
+
+impl PlayerStats {
+    fn total(&self, field: impl Fn(&CategoryStats) -> i64) -> i64 {
+        self.categories.iter().map(field).sum()
+    }
+}
+
+fn win_rate(won: i64, lost: i64) -> String {
+    if won + lost == 0 {
+        "-".into()
+    } else {
+        format!("{}%", won * 100 / (won + lost))
+    }
+}
+
+cfg_if! { if #[cfg(feature = "ssr")] {
+
+use sqlx::AnyPool;
+
+/// Records a finished game. Returns false, recording nothing, if a game with the same
+/// `game_id` already was.
+pub async fn insert_game(
+    pool: &AnyPool,
+    username: &str,
+    outcome: Outcome,
+    time_in_seconds: i64,
+    difficulty: Difficulty,
+    size: Size,
+    game_id: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let (outcome, difficulty, size) = (
+        outcome.to_string(),
+        difficulty.to_string(),
+        size.to_string(),
+    );
+
+    sqlx::query(
+        "
+            INSERT INTO games(username, outcome, time_in_seconds, difficulty, size, game_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (game_id) DO NOTHING
+        ",
+    )
+    .bind(username)
+    .bind(outcome)
+    .bind(time_in_seconds)
+    .bind(difficulty)
+    .bind(size)
+    .bind(game_id)
+    .execute(pool)
+    .await
+    .map(|result| result.rows_affected() > 0)
+}
+
+}}
+
+/// Records a lost or abandoned game. Won games are recorded by `post_score`.
+#[server(RecordGame)]
+pub async fn record_game(
+    username: String,
+    outcome: Outcome,
+    time_in_seconds: i64,
+    difficulty: Difficulty,
+    size: Size,
+) -> Result<(), ServerFnError> {
+    use crate::app_error::AppError;
+    use crate::metrics::Metrics;
+    use crate::pages::account::resolve_player;
+
+    if outcome == Outcome::Won {
+        return Err(ServerFnError::ServerError(
+            "Won games are recorded with their score".into(),
+        ));
+    }
+
+    let pool = expect_context::<sqlx::AnyPool>();
+    let username = resolve_player(&pool, username)
+        .await
+        .map_err(AppError::server_error)?;
+
+    insert_game(
+        &pool,
+        &username,
+        outcome,
+        time_in_seconds,
+        difficulty,
+        size,
+        None,
+    )
+    .await
+    .map_err(AppError::server_error)?;
+    expect_context::<Metrics>().game_finished(outcome, difficulty, size);
+
+    Ok(())
+}
+
+/// Counts a game as started, for the server's metrics.
+#[server(ReportGameStarted)]
+pub async fn report_game_started(difficulty: Difficulty, size: Size) -> Result<(), ServerFnError> {
+    use crate::metrics::Metrics;
+
+    expect_context::<Metrics>().game_started(difficulty, size);
+
+    Ok(())
+}
+
+#[server(GetStats)]
+async fn get_stats(username: String) -> Result<PlayerStats, ServerFnError> {
+    let pool = expect_context::<sqlx::AnyPool>();
+
+    let games = sqlx::query_as::<_, (String, i64, String, String)>(
+        "
+            SELECT outcome, time_in_seconds, difficulty, size
+            FROM games
+            WHERE username=$1
+            ORDER BY finished_at, id
+        ",
+    )
+    .bind(username)
+    .fetch_all(&pool)
+    .await?;
+
+    let mut stats = PlayerStats::default();
+    let mut streak = 0;
+
+    for (outcome, time_in_seconds, difficulty, size) in games {
+        let (Ok(outcome), Ok(difficulty), Ok(size)) = (
+            outcome.parse::<Outcome>(),
+            difficulty.parse::<Difficulty>(),
+            size.parse::<Size>(),
+        ) else {
+            continue;
+        };
+
+        let category = match stats
+            .categories
+            .iter()
+            .position(|category| category.difficulty == difficulty && category.size == size)
+        {
+            Some(index) => &mut stats.categories[index],
+            None => {
+                stats.categories.push(CategoryStats::new(difficulty, size));
+                stats.categories.last_mut().expect("just pushed")
+            }
+        };
+
+        match outcome {
+            Outcome::Won => {
+                category.won += 1;
+                category.trend.push(time_in_seconds);
+                streak += 1;
+                stats.best_streak = stats.best_streak.max(streak);
+            }
+            Outcome::Lost => {
+                category.lost += 1;
+                streak = 0;
+            }
+            Outcome::Abandoned => {
+                category.abandoned += 1;
+            }
+        }
+    }
+
+    stats.current_streak = streak;
+    stats
+        .categories
+        .sort_by_key(|category| (category.difficulty as u8, category.size as u8));
+
+    for category in &mut stats.categories {
+        category.best_time = category.trend.iter().copied().min();
+        category.average_time = (!category.trend.is_empty())
+            .then(|| category.trend.iter().sum::<i64>() / category.trend.len() as i64);
+
+        let skip = category.trend.len().saturating_sub(MAX_TREND_POINTS);
+        category.trend.drain(..skip);
+    }
+
+    Ok(stats)
+}
+
+/// Displays a player's game history.
+#[component]
+pub fn Stats() -> impl IntoView {
+    let params = use_params_map();
+    let username =
+        move || params.with(|params| params.get("username").cloned().unwrap_or_default());
+    let stats = create_resource(username, get_stats);
+    let i18n = use_i18n();
+
+    view! {
+        <div class="panel">
+            <div class="panel-label">
+                {move || i18n.t_with("stats.title", &[("name", &username())])}
+            </div>
+            <Transition fallback=|| ()>
+                {move || stats().map(|stats| match stats {
+                    Ok(stats) => view! { <StatsSummary stats /> }.into_view(),
+                    Err(_) => view! { <div class="panel-table">{move || i18n.t("stats.unavailable")}</div> }.into_view(),
+                })}
+            </Transition>
+        </div>
+
+        <div class="btns">
+            <div class="btn">
+                <A href="/">
+                    {move || i18n.t("common.return")}
+                </A>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn StatsSummary(stats: PlayerStats) -> impl IntoView {
+    let won = stats.total(|category| category.won);
+    let lost = stats.total(|category| category.lost);
+    let i18n = use_i18n();
+    let label = move |key| move || format!("{}:", i18n.t(key));
+
+    view! {
+        <table class="panel-table stats-summary">
+            <tr class="panel-row">
+                <td class="panel-row-label">{label("stats.played")}</td>
+                <td>{stats.total(CategoryStats::played)}</td>
+                <td class="panel-row-label">{label("stats.win_rate")}</td>
+                <td>{win_rate(won, lost)}</td>
+            </tr>
+            <tr class="panel-row">
+                <td class="panel-row-label">{label("stats.current_streak")}</td>
+                <td>{stats.current_streak}</td>
+                <td class="panel-row-label">{label("stats.best_streak")}</td>
+                <td>{stats.best_streak}</td>
+            </tr>
+        </table>
+
+        <table class="scoreboard stats">
+            <tr class="header">
+                <th>{move || i18n.t("stats.difficulty")}</th>
+                <th>{move || i18n.t("stats.size")}</th>
+                <th>{move || i18n.t("stats.played")}</th>
+                <th>{move || i18n.t("stats.won")}</th>
+                <th>{move || i18n.t("stats.lost")}</th>
+                <th>{move || i18n.t("stats.win_rate")}</th>
+                <th>{move || i18n.t("stats.best")}</th>
+                <th>{move || i18n.t("stats.average")}</th>
+                <th>{move || i18n.t("stats.trend")}</th>
+            </tr>
+            {stats
+                .categories
+                .into_iter()
+                .zip(1..)
+                .map(|(category, n)| {
+                    view! {
+                        <tr class={ if n % 2 == 0 { "even" } else { "odd" }}>
+                            <td>{move || i18n.t(&format!("difficulty.{}", category.difficulty))}</td>
+                            <td>{move || i18n.t(&format!("size.{}", category.size))}</td>
+                            <td>{category.played()}</td>
+                            <td>{category.won}</td>
+                            <td>{category.lost}</td>
+                            <td>{win_rate(category.won, category.lost)}</td>
+                            <td>{category.best_time.map(to_time)}</td>
+                            <td>{category.average_time.map(to_time)}</td>
+                            <td><Trend times=category.trend /></td>
+                        </tr>
+                    }
+                })
+                .collect_view()}
+        </table>
+    }
+}
+
+/// A sparkline of winning times, which rises as they get faster.
+#[component]
+fn Trend(times: Vec<i64>) -> impl IntoView {
+    let (min, max) = (
+        times.iter().copied().min().unwrap_or_default(),
+        times.iter().copied().max().unwrap_or_default(),
+    );
+    let step = TREND_WIDTH / (times.len().max(2) - 1) as f64;
+    let points = times
+        .iter()
+        .enumerate()
+        .map(|(i, &time)| {
+            let y = if max == min {
+                TREND_HEIGHT / 2.0
+            } else {
+                TREND_HEIGHT * (time - min) as f64 / (max - min) as f64
+            };
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    view! {
+        <svg
+            class="trend"
+            viewBox=format!("-2 -2 {} {}", TREND_WIDTH + 4.0, TREND_HEIGHT + 4.0)
+            width=TREND_WIDTH
+            height=TREND_HEIGHT
+        >
+            <polyline points=points />
+        </svg>
+    }
+}
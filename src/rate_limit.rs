@@ -0,0 +1,268 @@
+//! Limits how often each client may call server functions and post scores.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::{Body, Bytes, HttpBody},
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use leptos::{server_fn::ServerFn, ServerFnError};
+
+use crate::{app_error::AppError, config::Config, pages::scores::PostScore};
+
+const WINDOW: Duration = Duration::from_secs(60);
+/// How many clients are tracked before those whose windows have ended are forgotten.
+const PRUNE_AT: usize = 10_000;
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+/// Counts each client's requests in one minute windows.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    windows: Arc<Mutex<HashMap<String, Window>>>,
+}
+
+impl RateLimiter {
+    /// Allows each client `per_minute` requests a minute, or any number if it's 0.
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            windows: Default::default(),
+        }
+    }
+
+    /// Counts a request from `client`, or says how long until it may make another.
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        if self.per_minute == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut windows = self
+            .windows
+            .lock()
+            .expect("rate limiter lock isn't poisoned");
+        if windows.len() >= PRUNE_AT {
+            windows.retain(|_, window| now.duration_since(window.started) < WINDOW);
+        }
+
+        let window = windows.entry(client.to_owned()).or_insert(Window {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= WINDOW {
+            *window = Window {
+                started: now,
+                count: 0,
+            };
+        }
+
+        if window.count >= self.per_minute {
+            return Err(WINDOW - now.duration_since(window.started));
+        }
+        window.count += 1;
+
+        Ok(())
+    }
+}
+
+/// The limits on server function calls, as configured.
+#[derive(Clone, Debug)]
+pub struct RateLimits {
+    requests: RateLimiter,
+    scores_by_ip: RateLimiter,
+    scores_by_name: RateLimiter,
+    max_request_bytes: usize,
+    trust_forwarded_for: bool,
+}
+
+impl RateLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            requests: RateLimiter::new(config.requests_per_minute),
+            scores_by_ip: RateLimiter::new(config.scores_per_minute),
+            scores_by_name: RateLimiter::new(config.scores_per_minute),
+            max_request_bytes: config.max_request_bytes,
+            trust_forwarded_for: config.trust_forwarded_for,
+        }
+    }
+
+    /// Counts a score posted under a name. Only names the poster may use should be counted,
+    /// or anyone could use up another player's limit.
+    pub fn check_score_by(&self, username: &str) -> Result<(), Duration> {
+        self.scores_by_name.check(username)
+    }
+
+    /// The address a request came from. Behind a trusted proxy that's the last one in
+    /// X-Forwarded-For, as the proxy appends the address it was connected from.
+    fn client_ip(&self, addr: SocketAddr, headers: &HeaderMap) -> IpAddr {
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| headers.get_all("x-forwarded-for").iter().last())
+            .flatten()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+
+        forwarded.unwrap_or(addr.ip())
+    }
+}
+
+/// Middleware for the server function route. Clients over a limit get 429 Too Many
+/// Requests, and bodies over the size limit get 413 Payload Too Large.
+pub async fn limit_server_fns(
+    State(limits): State<RateLimits>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let ip = limits.client_ip(addr, request.headers()).to_string();
+    if let Err(retry_after) = limits.requests.check(&ip) {
+        return too_many_requests(retry_after);
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = match read_body(body, limits.max_request_bytes).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    // server functions are served from `/api/{url}`. `post_score` checks the limit by name
+    // itself, once it knows the name is the poster's.
+    if parts.uri.path().strip_prefix("/api/") == Some(<PostScore as ServerFn<()>>::url()) {
+        if let Err(retry_after) = limits.scores_by_ip.check(&ip) {
+            return too_many_requests(retry_after);
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+/// Reads the whole body, unless it's longer than `limit`.
+async fn read_body(mut body: Body, limit: usize) -> Result<Bytes, Response> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| {
+            server_fn_error(
+                StatusCode::BAD_REQUEST,
+                ServerFnError::ServerError(err.to_string()),
+            )
+        })?;
+        bytes.extend_from_slice(&chunk);
+
+        if bytes.len() > limit {
+            return Err(server_fn_error(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ServerFnError::ServerError("Request too large".into()),
+            ));
+        }
+    }
+
+    Ok(bytes.into())
+}
+
+/// A response the server function client reads as this error.
+fn server_fn_error(status: StatusCode, error: ServerFnError) -> Response {
+    let body = serde_json::to_string(&error).expect("server function errors serialize");
+
+    (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let mut response = server_fn_error(
+        StatusCode::TOO_MANY_REQUESTS,
+        AppError::server_error(AppError::RateLimited),
+    );
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(retry_after.as_secs().max(1)),
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(trust_forwarded_for: bool) -> RateLimits {
+        RateLimits {
+            requests: RateLimiter::new(0),
+            scores_by_ip: RateLimiter::new(0),
+            scores_by_name: RateLimiter::new(0),
+            max_request_bytes: 0,
+            trust_forwarded_for,
+        }
+    }
+
+    fn forwarded_for(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    const PEER: &str = "10.0.0.1:4000";
+
+    #[test]
+    fn forwarded_addresses_are_ignored_unless_trusted() {
+        let headers = forwarded_for(&["203.0.113.7"]);
+
+        assert_eq!(
+            limits(false).client_ip(PEER.parse().unwrap(), &headers),
+            "10.0.0.1".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_give_the_last_forwarded_address() {
+        let limits = limits(true);
+        let client_ip =
+            |values: &[&str]| limits.client_ip(PEER.parse().unwrap(), &forwarded_for(values));
+
+        assert_eq!(
+            client_ip(&["203.0.113.7"]),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(&["198.51.100.1, 203.0.113.7"]),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(&["198.51.100.1", " 2001:db8::1 "]),
+            "2001:db8::1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(
+            client_ip(&["not an address"]),
+            "10.0.0.1".parse::<IpAddr>().unwrap()
+        );
+        assert_eq!(client_ip(&[]), "10.0.0.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn limiters_count_each_client_separately() {
+        let limiter = RateLimiter::new(2);
+
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_ok());
+        assert!(limiter.check("a").is_err());
+        assert!(limiter.check("b").is_ok());
+
+        let unlimited = RateLimiter::new(0);
+        assert!((0..100).all(|_| unlimited.check("a").is_ok()));
+    }
+}
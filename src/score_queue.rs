@@ -0,0 +1,111 @@
+//! Scores waiting to reach the server. Each is kept in local storage until the server
+//! answers, so one won while offline is sent again on the next visit, or when the
+//! browser comes back online. The server ignores scores whose game it already recorded,
+//! so sending one twice is harmless.
+
+use std::cell::Cell;
+
+use gloo_storage::{LocalStorage, Storage};
+use leptos::ServerFnError;
+
+use crate::{
+    app_error::AppError,
+    pages::scores::{post_score, PostScore},
+};
+
+const QUEUE_KEY: &str = "score_queue";
+
+thread_local! {
+    static SENDING_QUEUED: Cell<bool> = Cell::new(false);
+}
+
+/// A new id for a game, so the server can tell if its score was sent before.
+pub fn new_game_id() -> String {
+    format!(
+        "{:016x}{:016x}",
+        rand::random::<u64>(),
+        rand::random::<u64>()
+    )
+}
+
+fn queued() -> Vec<PostScore> {
+    LocalStorage::get(QUEUE_KEY).unwrap_or_default()
+}
+
+fn save(queue: &[PostScore]) {
+    let saved = if queue.is_empty() {
+        LocalStorage::delete(QUEUE_KEY);
+        Ok(())
+    } else {
+        LocalStorage::set(QUEUE_KEY, queue)
+    };
+
+    if let Err(err) = saved {
+        log::warn!("couldn't save unsent scores: {err}");
+    }
+}
+
+fn enqueue(score: &PostScore) {
+    let mut queue = queued();
+    if !queue.iter().any(|queued| queued.game_id == score.game_id) {
+        queue.push(score.clone());
+        save(&queue);
+    }
+}
+
+/// Takes a score off the queue, unless sending it failed in a way that might not last.
+fn settle(game_id: &str, result: &Result<(), ServerFnError>) -> bool {
+    let retry = match result {
+        Ok(()) => false,
+        Err(error) => AppError::from_server_error(error).is_retryable(),
+    };
+
+    if !retry {
+        let mut queue = queued();
+        queue.retain(|queued| queued.game_id != game_id);
+        save(&queue);
+    }
+
+    retry
+}
+
+async fn post(score: PostScore) -> Result<(), ServerFnError> {
+    let PostScore {
+        username,
+        time_in_seconds,
+        difficulty,
+        size,
+        game_id,
+    } = score;
+
+    post_score(username, time_in_seconds, difficulty, size, game_id).await
+}
+
+/// Sends a score, keeping it queued until the server has answered.
+pub async fn send(score: PostScore) -> Result<(), ServerFnError> {
+    enqueue(&score);
+
+    let game_id = score.game_id.clone();
+    let result = post(score).await;
+    settle(&game_id, &result);
+
+    result
+}
+
+/// Sends every queued score, oldest first, stopping at the first the server can't take.
+pub async fn send_queued() {
+    if SENDING_QUEUED.with(|sending| sending.replace(true)) {
+        return;
+    }
+
+    for score in queued() {
+        let game_id = score.game_id.clone();
+        let result = post(score).await;
+
+        if settle(&game_id, &result) {
+            break;
+        }
+    }
+
+    SENDING_QUEUED.with(|sending| sending.set(false));
+}
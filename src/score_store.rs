@@ -0,0 +1,478 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use async_trait::async_trait;
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::AnyPool;
+
+use crate::{
+    database::contains_pattern,
+    game_settings::{Difficulty, Size},
+    pages::scores::{Period, Score, ScoreMode, ScorePage},
+};
+
+/// The store the server was started with, shared by server functions and the REST API.
+pub type SharedScoreStore = Arc<dyn ScoreStore>;
+
+/// The highest page number clients may ask for, which keeps offsets well within range.
+pub const MAX_PAGE: i64 = 1_000_000;
+
+/// Filters for reading a page of a scoreboard, shared by `get_scores` and the REST API.
+pub struct ScoreQuery {
+    pub difficulty: Difficulty,
+    pub size: Size,
+    pub period: Period,
+    pub mode: ScoreMode,
+    /// Only include names containing this, ignoring case.
+    pub search: String,
+    /// Only include this exact name.
+    pub username: Option<String>,
+    /// Numbered from 1.
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl ScoreQuery {
+    fn offset(&self) -> i64 {
+        page_offset(self.page, self.per_page)
+    }
+}
+
+/// How many rows come before a page. Pages below 1 are taken to be the first.
+fn page_offset(page: i64, per_page: i64) -> i64 {
+    (page.clamp(1, MAX_PAGE) - 1).saturating_mul(per_page)
+}
+
+/// A row of the `scores` table, for exporting it whole.
+#[derive(Clone, Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ScoreRecord {
+    pub id: i64,
+    pub username: String,
+    pub time_in_seconds: i64,
+    pub difficulty: String,
+    pub size: String,
+    pub created_at: String,
+}
+
+/// Keeps the winning times that make up the scoreboards.
+#[async_trait]
+pub trait ScoreStore: Debug + Send + Sync {
+    /// Adds a score. Returns false, adding nothing, if one with the same `game_id` is kept
+    /// already, so a score that's sent again isn't counted twice.
+    async fn insert_score(
+        &self,
+        username: &str,
+        time_in_seconds: i64,
+        difficulty: Difficulty,
+        size: Size,
+        game_id: Option<&str>,
+    ) -> Result<bool, sqlx::Error>;
+
+    /// Fetches a page of scores. Ranks are positions on the whole board, so they aren't
+    /// affected by filtering names.
+    async fn fetch_scores(&self, query: &ScoreQuery) -> Result<ScorePage, sqlx::Error>;
+
+    /// Every score on every board, oldest first.
+    fn score_records(&self) -> BoxStream<'_, Result<ScoreRecord, sqlx::Error>>;
+
+    /// A page of the scores on every board whose names contain `search`, newest first,
+    /// along with how many there are in all.
+    async fn recent_records(
+        &self,
+        search: &str,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<ScoreRecord>, i64), sqlx::Error>;
+
+    /// Deletes a score, returning it if it existed.
+    async fn delete_score(&self, id: i64) -> Result<Option<ScoreRecord>, sqlx::Error>;
+
+    /// Deletes every score posted under a name, returning how many there were.
+    async fn delete_scores_by(&self, username: &str) -> Result<u64, sqlx::Error>;
+
+    /// The best rank a player holds on a board, if they have any scores on it.
+    async fn best_rank(
+        &self,
+        difficulty: Difficulty,
+        size: Size,
+        period: Period,
+        mode: ScoreMode,
+        username: String,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let query = ScoreQuery {
+            difficulty,
+            size,
+            period,
+            mode,
+            search: String::new(),
+            username: Some(username),
+            page: 1,
+            per_page: 1,
+        };
+
+        let score_page = self.fetch_scores(&query).await?;
+        Ok(score_page.scores.first().map(|score| score.rank))
+    }
+}
+
+#[async_trait]
+impl ScoreStore for AnyPool {
+    async fn insert_score(
+        &self,
+        username: &str,
+        time_in_seconds: i64,
+        difficulty: Difficulty,
+        size: Size,
+        game_id: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query(
+            "
+                INSERT INTO scores(username, time_in_seconds, difficulty, size, game_id)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (game_id) DO NOTHING
+            ",
+        )
+        .bind(username)
+        .bind(time_in_seconds)
+        .bind(difficulty.to_string())
+        .bind(size.to_string())
+        .bind(game_id)
+        .execute(self)
+        .await
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    async fn fetch_scores(&self, query: &ScoreQuery) -> Result<ScorePage, sqlx::Error> {
+        let (difficulty, size) = (query.difficulty.to_string(), query.size.to_string());
+        let since = query.period.start().unwrap_or_default();
+        let all_runs = query.mode == ScoreMode::All;
+        let search = contains_pattern(&query.search);
+        let username = query.username.clone().unwrap_or_default();
+
+        // `attempt` numbers each player's runs from fastest to slowest, so their best has attempt 1
+        let scores = sqlx::query_as::<_, Score>(
+            "
+                SELECT rank, username, time_in_seconds
+                FROM (
+                    SELECT ROW_NUMBER() OVER (ORDER BY time_in_seconds, id) AS rank,
+                        username,
+                        time_in_seconds
+                    FROM (
+                        SELECT id,
+                            username,
+                            time_in_seconds,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY username
+                                ORDER BY time_in_seconds, id
+                            ) AS attempt
+                        FROM scores
+                        WHERE difficulty=$1
+                            AND size=$2
+                            AND created_at>=$3
+                    ) AS attempts
+                    WHERE attempt=1 OR $4
+                ) AS ranked
+                WHERE lower(username) LIKE $5 ESCAPE '\\'
+                    AND ($6='' OR username=$6)
+                ORDER BY rank
+                LIMIT $7
+                OFFSET $8
+            ",
+        )
+        .bind(&difficulty)
+        .bind(&size)
+        .bind(&since)
+        .bind(all_runs)
+        .bind(&search)
+        .bind(&username)
+        .bind(query.per_page)
+        .bind(query.offset())
+        .fetch_all(self)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            "
+                SELECT COUNT(*)
+                FROM (
+                    SELECT username,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY username
+                            ORDER BY time_in_seconds, id
+                        ) AS attempt
+                    FROM scores
+                    WHERE difficulty=$1
+                        AND size=$2
+                        AND created_at>=$3
+                ) AS attempts
+                WHERE (attempt=1 OR $4)
+                    AND lower(username) LIKE $5 ESCAPE '\\'
+                    AND ($6='' OR username=$6)
+            ",
+        )
+        .bind(&difficulty)
+        .bind(&size)
+        .bind(&since)
+        .bind(all_runs)
+        .bind(&search)
+        .bind(&username)
+        .fetch_one(self)
+        .await?;
+
+        Ok(ScorePage {
+            scores,
+            total,
+            per_page: query.per_page,
+        })
+    }
+
+    fn score_records(&self) -> BoxStream<'_, Result<ScoreRecord, sqlx::Error>> {
+        sqlx::query_as(
+            "
+                SELECT id, username, time_in_seconds, difficulty, size, created_at
+                FROM scores
+                ORDER BY id
+            ",
+        )
+        .fetch(self)
+    }
+
+    async fn recent_records(
+        &self,
+        search: &str,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<ScoreRecord>, i64), sqlx::Error> {
+        let search = contains_pattern(search);
+
+        let records = sqlx::query_as(
+            "
+                SELECT id, username, time_in_seconds, difficulty, size, created_at
+                FROM scores
+                WHERE lower(username) LIKE $1 ESCAPE '\\'
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2
+                OFFSET $3
+            ",
+        )
+        .bind(&search)
+        .bind(per_page)
+        .bind(page_offset(page, per_page))
+        .fetch_all(self)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM scores WHERE lower(username) LIKE $1 ESCAPE '\\'",
+        )
+        .bind(&search)
+        .fetch_one(self)
+        .await?;
+
+        Ok((records, total))
+    }
+
+    async fn delete_score(&self, id: i64) -> Result<Option<ScoreRecord>, sqlx::Error> {
+        sqlx::query_as(
+            "
+                DELETE FROM scores
+                WHERE id=$1
+                RETURNING id, username, time_in_seconds, difficulty, size, created_at
+            ",
+        )
+        .bind(id)
+        .fetch_optional(self)
+        .await
+    }
+
+    async fn delete_scores_by(&self, username: &str) -> Result<u64, sqlx::Error> {
+        sqlx::query("DELETE FROM scores WHERE username=$1")
+            .bind(username)
+            .execute(self)
+            .await
+            .map(|result| result.rows_affected())
+    }
+}
+
+/// Keeps scores only for as long as the server runs, for demos and tests.
+#[derive(Debug, Default)]
+pub struct MemoryScoreStore {
+    scores: RwLock<MemoryScores>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryScores {
+    records: Vec<ScoreRecord>,
+    /// The id of the newest score, which stays taken after it's deleted, like database ids.
+    last_id: i64,
+    /// The id of the score posted with each game id.
+    game_ids: HashMap<String, i64>,
+}
+
+impl MemoryScoreStore {
+    fn read(&self) -> RwLockReadGuard<'_, MemoryScores> {
+        self.scores.read().expect("score store lock isn't poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MemoryScores> {
+        self.scores
+            .write()
+            .expect("score store lock isn't poisoned")
+    }
+}
+
+#[async_trait]
+impl ScoreStore for MemoryScoreStore {
+    async fn insert_score(
+        &self,
+        username: &str,
+        time_in_seconds: i64,
+        difficulty: Difficulty,
+        size: Size,
+        game_id: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut scores = self.write();
+        if game_id.is_some_and(|game_id| scores.game_ids.contains_key(game_id)) {
+            return Ok(false);
+        }
+
+        scores.last_id += 1;
+        let id = scores.last_id;
+        if let Some(game_id) = game_id {
+            scores.game_ids.insert(game_id.to_owned(), id);
+        }
+        scores.records.push(ScoreRecord {
+            id,
+            username: username.to_owned(),
+            time_in_seconds,
+            difficulty: difficulty.to_string(),
+            size: size.to_string(),
+            created_at: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+
+        Ok(true)
+    }
+
+    async fn fetch_scores(&self, query: &ScoreQuery) -> Result<ScorePage, sqlx::Error> {
+        let (difficulty, size) = (query.difficulty.to_string(), query.size.to_string());
+        let since = query.period.start().unwrap_or_default();
+        let search = query.search.to_lowercase();
+
+        let scores = self.read();
+        let mut board = scores
+            .records
+            .iter()
+            .filter(|record| {
+                record.difficulty == difficulty && record.size == size && record.created_at >= since
+            })
+            .collect::<Vec<_>>();
+        board.sort_by_key(|record| (record.time_in_seconds, record.id));
+
+        if query.mode == ScoreMode::Best {
+            let mut ranked = HashSet::new();
+            board.retain(|record| ranked.insert(&record.username));
+        }
+
+        let matching = board
+            .into_iter()
+            .zip(1..)
+            .filter(|(record, _)| {
+                record.username.to_lowercase().contains(&search)
+                    && query
+                        .username
+                        .as_ref()
+                        .map_or(true, |username| &record.username == username)
+            })
+            .map(|(record, rank)| Score {
+                rank,
+                username: record.username.clone(),
+                time_in_seconds: record.time_in_seconds,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(ScorePage {
+            total: matching.len() as i64,
+            scores: matching
+                .into_iter()
+                .skip(query.offset() as usize)
+                .take(query.per_page as usize)
+                .collect(),
+            per_page: query.per_page,
+        })
+    }
+
+    fn score_records(&self) -> BoxStream<'_, Result<ScoreRecord, sqlx::Error>> {
+        let records = self.read().records.clone();
+        stream::iter(records.into_iter().map(Ok)).boxed()
+    }
+
+    async fn recent_records(
+        &self,
+        search: &str,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<ScoreRecord>, i64), sqlx::Error> {
+        let search = search.to_lowercase();
+
+        let mut matching = self
+            .read()
+            .records
+            .iter()
+            .filter(|record| record.username.to_lowercase().contains(&search))
+            .cloned()
+            .collect::<Vec<_>>();
+        matching.sort_by(|a, b| (&b.created_at, b.id).cmp(&(&a.created_at, a.id)));
+
+        let total = matching.len() as i64;
+        let records = matching
+            .into_iter()
+            .skip(page_offset(page, per_page) as usize)
+            .take(per_page as usize)
+            .collect();
+
+        Ok((records, total))
+    }
+
+    async fn delete_score(&self, id: i64) -> Result<Option<ScoreRecord>, sqlx::Error> {
+        let mut scores = self.write();
+        let Some(index) = scores.records.iter().position(|record| record.id == id) else {
+            return Ok(None);
+        };
+
+        scores.game_ids.retain(|_, score_id| *score_id != id);
+        Ok(Some(scores.records.remove(index)))
+    }
+
+    async fn delete_scores_by(&self, username: &str) -> Result<u64, sqlx::Error> {
+        let mut scores = self.write();
+        let before = scores.records.len();
+        scores.records.retain(|record| record.username != username);
+
+        let MemoryScores {
+            records, game_ids, ..
+        } = &mut *scores;
+        game_ids.retain(|_, id| records.iter().any(|record| record.id == *id));
+
+        Ok((before - records.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_offsets_stay_in_range() {
+        assert_eq!(page_offset(1, 10), 0);
+        assert_eq!(page_offset(3, 10), 20);
+        assert_eq!(page_offset(0, 10), 0);
+        assert_eq!(page_offset(i64::MIN, 10), 0);
+        assert_eq!(page_offset(i64::MAX, 10), (MAX_PAGE - 1) * 10);
+        assert_eq!(page_offset(MAX_PAGE, i64::MAX), i64::MAX);
+    }
+}
@@ -0,0 +1,54 @@
+//! Server logs, written through `tracing` as text or JSON lines. Records from the `log`
+//! crate, which leptos and sqlx use, are passed along too.
+
+use axum::{body::Body, http::Request};
+use tracing::{span::Id, Span};
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+
+use crate::config::{Config, LogFormat};
+
+/// The header each request's ID is read from, or added under if it has none.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Starts writing logs as configured. Spans are logged as they close, with how long they took.
+pub fn init(config: &Config) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_new(&config.log_level)?)
+        .with_span_events(FmtSpan::CLOSE);
+
+    match config.log_format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(true)
+            .try_init(),
+    }
+}
+
+/// The span everything done for a request is logged under, so its lines can be found by ID.
+pub fn request_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+    )
+}
+
+/// The span of the request a server function was called by. Leptos runs server functions on
+/// their own task, so it's provided as context rather than being the current span.
+#[derive(Clone, Debug)]
+pub struct RequestSpan(pub Span);
+
+/// The parent for server function spans, for use in `#[instrument(parent = ...)]`.
+pub fn request_span_id() -> Option<Id> {
+    leptos::use_context::<RequestSpan>().and_then(|RequestSpan(span)| span.id())
+}
@@ -1,3 +1,5 @@
+use leptos::ServerFnError;
+
 pub fn to_title(s: &impl ToString) -> String {
     let mut s = s.to_string();
     s[..1].make_ascii_uppercase();
@@ -12,3 +14,11 @@ pub fn to_time(seconds: i64) -> String {
         duration.num_seconds() % 60
     )
 }
+
+/// The message of a server function error, as the server wrote it.
+pub fn error_message(error: ServerFnError) -> String {
+    match error {
+        ServerFnError::ServerError(message) => message,
+        error => error.to_string(),
+    }
+}
@@ -0,0 +1,232 @@
+//! Checks that the in-memory store ranks, filters and pages scoreboards the same way the
+//! database does.
+
+use rustsweeper::{
+    database,
+    game_settings::{Difficulty, Size},
+    pages::scores::{Period, ScoreMode},
+    score_store::{MemoryScoreStore, ScoreQuery, ScoreStore},
+};
+use sqlx::AnyPool;
+
+const SCORES: &[(&str, i64, Difficulty, Size)] = &[
+    ("alice", 30, Difficulty::Easy, Size::Small),
+    ("bob", 25, Difficulty::Easy, Size::Small),
+    ("alice", 20, Difficulty::Easy, Size::Small),
+    ("carol", 40, Difficulty::Easy, Size::Small),
+    ("caroline", 25, Difficulty::Easy, Size::Small),
+    ("dave", 5, Difficulty::Hard, Size::Small),
+    ("dave", 6, Difficulty::Easy, Size::Large),
+];
+
+async fn stores() -> [Box<dyn ScoreStore>; 2] {
+    let pool: AnyPool = database::connect_in_memory().await.unwrap();
+    database::migrate(&pool).await.unwrap();
+    let stores: [Box<dyn ScoreStore>; 2] = [Box::<MemoryScoreStore>::default(), Box::new(pool)];
+
+    for store in &stores {
+        for (game, &(username, time, difficulty, size)) in SCORES.iter().enumerate() {
+            let game_id = game.to_string();
+            let inserted = store
+                .insert_score(username, time, difficulty, size, Some(&game_id))
+                .await
+                .unwrap();
+            assert!(inserted, "{store:?}");
+        }
+    }
+
+    stores
+}
+
+fn query(mode: ScoreMode, search: &str, page: i64, per_page: i64) -> ScoreQuery {
+    ScoreQuery {
+        difficulty: Difficulty::Easy,
+        size: Size::Small,
+        period: Period::All,
+        mode,
+        search: search.into(),
+        username: None,
+        page,
+        per_page,
+    }
+}
+
+/// Each score's rank, name and time, along with how many scores match.
+async fn board(store: &dyn ScoreStore, query: &ScoreQuery) -> (Vec<(i64, String, i64)>, i64) {
+    let page = store.fetch_scores(query).await.unwrap();
+    let scores = page
+        .scores
+        .into_iter()
+        .map(|score| (score.rank, score.username, score.time_in_seconds))
+        .collect();
+
+    (scores, page.total)
+}
+
+fn rows(rows: &[(i64, &str, i64)]) -> Vec<(i64, String, i64)> {
+    rows.iter()
+        .map(|&(rank, username, time)| (rank, username.to_owned(), time))
+        .collect()
+}
+
+#[tokio::test]
+async fn best_mode_keeps_each_players_fastest_time() {
+    for store in stores().await {
+        let expected = rows(&[
+            (1, "alice", 20),
+            (2, "bob", 25),
+            (3, "caroline", 25),
+            (4, "carol", 40),
+        ]);
+
+        assert_eq!(
+            board(store.as_ref(), &query(ScoreMode::Best, "", 1, 10)).await,
+            (expected, 4),
+            "{store:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn all_mode_ranks_every_run() {
+    for store in stores().await {
+        let expected = rows(&[
+            (1, "alice", 20),
+            (2, "bob", 25),
+            (3, "caroline", 25),
+            (4, "alice", 30),
+            (5, "carol", 40),
+        ]);
+
+        assert_eq!(
+            board(store.as_ref(), &query(ScoreMode::All, "", 1, 10)).await,
+            (expected, 5),
+            "{store:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn search_keeps_board_ranks() {
+    for store in stores().await {
+        let expected = rows(&[(3, "caroline", 25), (4, "carol", 40)]);
+
+        assert_eq!(
+            board(store.as_ref(), &query(ScoreMode::Best, "CAR", 1, 10)).await,
+            (expected, 2),
+            "{store:?}"
+        );
+        assert_eq!(
+            board(store.as_ref(), &query(ScoreMode::Best, "%", 1, 10)).await,
+            (vec![], 0),
+            "{store:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn pages_split_the_board() {
+    for store in stores().await {
+        let store = store.as_ref();
+
+        assert_eq!(
+            board(store, &query(ScoreMode::All, "", 2, 2)).await,
+            (rows(&[(3, "caroline", 25), (4, "alice", 30)]), 5),
+            "{store:?}"
+        );
+        assert_eq!(
+            board(store, &query(ScoreMode::All, "", 0, 2)).await,
+            board(store, &query(ScoreMode::All, "", 1, 2)).await,
+            "{store:?}"
+        );
+        assert_eq!(
+            board(store, &query(ScoreMode::All, "", i64::MAX, 2)).await,
+            (vec![], 5),
+            "{store:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn best_rank_finds_a_players_place() {
+    for store in stores().await {
+        for (mode, carol) in [(ScoreMode::Best, 4), (ScoreMode::All, 5)] {
+            let best_rank = |username: &str| {
+                store.best_rank(
+                    Difficulty::Easy,
+                    Size::Small,
+                    Period::All,
+                    mode,
+                    username.into(),
+                )
+            };
+
+            assert_eq!(best_rank("carol").await.unwrap(), Some(carol), "{store:?}");
+            assert_eq!(best_rank("dave").await.unwrap(), None, "{store:?}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn admin_listing_and_deletes_match() {
+    for store in stores().await {
+        let (records, total) = store.recent_records("dave", 1, 10).await.unwrap();
+        let ids = records.iter().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!((ids, total), (vec![7, 6], 2), "{store:?}");
+
+        let (records, total) = store.recent_records("", 2, 3).await.unwrap();
+        let ids = records.iter().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!((ids, total), (vec![4, 3, 2], 7), "{store:?}");
+
+        let deleted = store.delete_score(2).await.unwrap();
+        assert_eq!(
+            deleted.map(|record| record.username),
+            Some("bob".into()),
+            "{store:?}"
+        );
+        assert!(store.delete_score(2).await.unwrap().is_none(), "{store:?}");
+
+        assert_eq!(
+            store.delete_scores_by("alice").await.unwrap(),
+            2,
+            "{store:?}"
+        );
+        assert_eq!(
+            board(store.as_ref(), &query(ScoreMode::Best, "", 1, 10)).await,
+            (rows(&[(1, "caroline", 25), (2, "carol", 40)]), 2),
+            "{store:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn deleted_ids_are_not_given_again() {
+    for store in stores().await {
+        let newest = SCORES.len() as i64;
+        store.delete_score(newest).await.unwrap();
+        store
+            .insert_score("erin", 10, Difficulty::Easy, Size::Small, None)
+            .await
+            .unwrap();
+
+        let (records, _) = store.recent_records("erin", 1, 10).await.unwrap();
+        let ids = records.iter().map(|record| record.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![newest + 1], "{store:?}");
+    }
+}
+
+#[tokio::test]
+async fn scores_sent_again_are_kept_once() {
+    for store in stores().await {
+        let insert =
+            |game_id| store.insert_score("erin", 10, Difficulty::Easy, Size::Small, game_id);
+
+        assert!(!insert(Some("0")).await.unwrap(), "{store:?}");
+        assert!(insert(None).await.unwrap(), "{store:?}");
+        assert!(insert(None).await.unwrap(), "{store:?}");
+
+        store.delete_score(1).await.unwrap();
+        assert!(insert(Some("0")).await.unwrap(), "{store:?}");
+        assert!(!insert(Some("0")).await.unwrap(), "{store:?}");
+    }
+}